@@ -0,0 +1,86 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Waker};
+
+/// A handle to cancel a running transfer.
+///
+/// Cancelling stops the transfer at the next chunk and fails it with an error for which
+/// [`Error::is_cancelled`](crate::Error::is_cancelled) returns true. Clones of the handle
+/// cancel the same transfers.
+///
+/// # Example
+/// ```no_run
+/// use modio::CancelHandle;
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// #     let modio = modio::Modio::new("api-key")?;
+///
+/// let cancel = CancelHandle::new();
+/// let download = modio
+///     .download((5, 19))
+///     .cancel_handle(cancel.clone())
+///     .save_to_file("mod.zip");
+///
+/// // Somewhere else, e.g. when the user clicks the cancel button.
+/// cancel.cancel();
+///
+/// match download.await {
+///     Err(e) if e.is_cancelled() => println!("download cancelled"),
+///     res => res?,
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct CancelHandle {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl CancelHandle {
+    pub fn new() -> CancelHandle {
+        CancelHandle::default()
+    }
+
+    /// Cancel the transfers using this handle.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        let wakers = std::mem::take(&mut *self.inner.wakers.lock().unwrap());
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Returns true if the handle has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Returns true if the handle has been cancelled, otherwise the task is woken up
+    /// once it is cancelled.
+    pub(crate) fn poll_cancelled(&self, cx: &mut Context<'_>) -> bool {
+        if self.is_cancelled() {
+            return true;
+        }
+        {
+            let mut wakers = self.inner.wakers.lock().unwrap();
+            if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+        }
+        self.is_cancelled()
+    }
+}
+
+impl fmt::Debug for CancelHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancelHandle")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
@@ -6,13 +6,19 @@ use http::header::USER_AGENT;
 use http::header::{HeaderMap, HeaderValue};
 
 use crate::auth::{Auth, Credentials, Token};
-use crate::download::{DownloadAction, Downloader};
+use crate::download::{DownloadAction, Downloader, Throttle, VirusScanPolicy};
 use crate::error::{self, Error, Result};
 use crate::games::{GameRef, Games};
 use crate::mods::ModRef;
+use crate::ratelimit::RateLimiter;
 use crate::reports::Reports;
 use crate::request::RequestBuilder;
+use crate::response::{ResponseHook, ResponseMeta};
+use crate::retry::RetryPolicy;
 use crate::routing::Route;
+#[cfg(feature = "tower")]
+use crate::transport::{BoxError, Request, Response, ServiceTransport, TransportService};
+use crate::transport::{SharedTransport, Transport};
 use crate::user::Me;
 use crate::{TargetPlatform, TargetPortal};
 
@@ -78,6 +84,15 @@ struct Config {
     builder: Option<ClientBuilder>,
     headers: HeaderMap,
     proxies: Vec<Proxy>,
+    retry: RetryPolicy,
+    ratelimit_reserve: Option<u32>,
+    on_response: Option<ResponseHook>,
+    target_platform: Option<TargetPlatform>,
+    throttle: Option<Throttle>,
+    virus_scan: Option<VirusScanPolicy>,
+    transport: Option<SharedTransport>,
+    #[cfg(feature = "tower")]
+    layers: Vec<Box<dyn FnOnce(SharedTransport) -> SharedTransport + Send>>,
     #[cfg(feature = "__tls")]
     tls: TlsBackend,
     error: Option<Error>,
@@ -92,6 +107,7 @@ enum TlsBackend {
 }
 
 #[cfg(feature = "__tls")]
+#[allow(clippy::derivable_impls)]
 impl Default for TlsBackend {
     fn default() -> TlsBackend {
         #[cfg(feature = "default-tls")]
@@ -117,6 +133,15 @@ impl Builder {
                 builder: None,
                 headers: HeaderMap::new(),
                 proxies: Vec::new(),
+                retry: RetryPolicy::default(),
+                ratelimit_reserve: None,
+                on_response: None,
+                target_platform: None,
+                throttle: None,
+                virus_scan: None,
+                transport: None,
+                #[cfg(feature = "tower")]
+                layers: Vec::new(),
                 #[cfg(feature = "__tls")]
                 tls: TlsBackend::default(),
                 error: None,
@@ -135,6 +160,11 @@ impl Builder {
         let host = config.host.unwrap_or_else(|| DEFAULT_HOST.to_string());
         let credentials = config.credentials;
 
+        let mut headers = config.headers;
+        if !headers.contains_key(USER_AGENT) {
+            headers.insert(USER_AGENT, HeaderValue::from_static(DEFAULT_AGENT));
+        }
+
         let client = {
             let mut builder = {
                 let builder = config.builder.unwrap_or_else(Client::builder);
@@ -150,31 +180,41 @@ impl Builder {
                 builder
             };
 
-            let mut headers = config.headers;
-            if !headers.contains_key(USER_AGENT) {
-                headers.insert(USER_AGENT, HeaderValue::from_static(DEFAULT_AGENT));
-            }
-
             for proxy in config.proxies {
                 builder = builder.proxy(proxy);
             }
 
-            builder
-                .default_headers(headers)
-                .build()
-                .map_err(error::builder)?
+            builder.build().map_err(error::builder)?
         };
+        let transport = config
+            .transport
+            .unwrap_or_else(|| SharedTransport::new(client.clone()));
+        #[cfg(feature = "tower")]
+        let transport = config
+            .layers
+            .into_iter()
+            .fold(transport, |transport, layer| layer(transport));
 
         Ok(Modio {
             inner: Arc::new(ClientRef {
                 host,
                 client,
+                transport,
+                headers,
                 credentials,
+                retry: config.retry,
+                ratelimit: config.ratelimit_reserve.map(RateLimiter::new),
+                on_response: config.on_response,
+                target_platform: config.target_platform,
+                throttle: config.throttle,
+                virus_scan: config.virus_scan,
             }),
         })
     }
 
     /// Configure the underlying `reqwest` client using `reqwest::ClientBuilder`.
+    ///
+    /// The client is the default [`Transport`] if no custom transport is set.
     pub fn client<F>(mut self, f: F) -> Builder
     where
         F: FnOnce(ClientBuilder) -> ClientBuilder,
@@ -222,13 +262,130 @@ impl Builder {
         self
     }
 
+    /// Set the policy for retrying failed requests.
+    ///
+    /// Defaults to [`RetryPolicy::none()`].
+    pub fn retry(mut self, policy: RetryPolicy) -> Builder {
+        self.config.retry = policy;
+        self
+    }
+
+    /// Keep a client-side budget of the rate limit and delay requests before it is exhausted.
+    ///
+    /// The budget is updated from the `X-RateLimit-*` headers of every response and shared by
+    /// all clones of the `Modio` client. Once the remaining requests drop to `reserve`, further
+    /// requests are delayed until the budget has recovered, instead of failing with
+    /// [`Error::is_ratelimited`].
+    ///
+    /// Clients returned by [`Modio::with_credentials`] and [`Modio::with_token`] start with a
+    /// budget of their own.
+    pub fn ratelimit_budget(mut self, reserve: u32) -> Builder {
+        self.config.ratelimit_reserve = Some(reserve);
+        self
+    }
+
+    /// Register a callback that is called with the [`ResponseMeta`] of every response.
+    ///
+    /// The metadata contains the status, the request id and the rate limit headers of
+    /// successful and failed requests.
+    /// [`ResponseMeta::capture`] returns the metadata of a single call instead.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn main() -> modio::Result<()> {
+    /// let modio = modio::Modio::builder("user-or-game-api-key")
+    ///     .on_response(|meta| {
+    ///         println!(
+    ///             "{} request-id: {:?} remaining: {:?}",
+    ///             meta.status, meta.request_id, meta.ratelimit_remaining
+    ///         );
+    ///     })
+    ///     .build()?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn on_response<F>(mut self, f: F) -> Builder
+    where
+        F: Fn(&ResponseMeta) + Send + Sync + 'static,
+    {
+        self.config.on_response = Some(ResponseHook::new(f));
+        self
+    }
+
+    /// Limit the bandwidth of all downloads of the client with the [`Throttle`].
+    ///
+    /// The throttle is shared by all clones of the `Modio` client and can be adjusted at
+    /// runtime with [`Throttle::set_limit`].
+    pub fn throttle(mut self, throttle: Throttle) -> Builder {
+        self.config.throttle = Some(throttle);
+        self
+    }
+
+    /// Set the [`VirusScanPolicy`] for all downloads of the client.
+    ///
+    /// Defaults to [`VirusScanPolicy::Allow`]. The policy can be overridden per download with
+    /// [`Downloader::virus_scan_policy`](crate::download::Downloader::virus_scan_policy).
+    pub fn virus_scan_policy(mut self, policy: VirusScanPolicy) -> Builder {
+        self.config.virus_scan = Some(policy);
+        self
+    }
+
+    /// Set a custom [`Transport`] that sends all requests of the client.
+    ///
+    /// Defaults to the `reqwest` client. See the [`transport`](crate::transport) module.
+    pub fn transport<T: Transport>(mut self, transport: T) -> Builder {
+        self.config.transport = Some(SharedTransport::new(transport));
+        self
+    }
+
+    /// Wrap the transport of the client with a [`tower::Layer`].
+    ///
+    /// The layer is applied to a [`TransportService`] which sends the requests with the
+    /// configured transport. Calling `layer` multiple times wraps the existing stack, the last
+    /// added layer is the outermost. The built service is shared by all requests and isn't
+    /// cloned, see [`ServiceTransport`](crate::transport::ServiceTransport).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use tower::ServiceBuilder;
+    /// # fn main() -> modio::Result<()> {
+    ///
+    /// let modio = modio::Modio::builder("user-or-game-api-key")
+    ///     .layer(
+    ///         ServiceBuilder::new()
+    ///             .concurrency_limit(4)
+    ///             .timeout(Duration::from_secs(10)),
+    ///     )
+    ///     .build()?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "tower")]
+    pub fn layer<L>(mut self, layer: L) -> Builder
+    where
+        L: tower::Layer<TransportService> + Send + 'static,
+        L::Service: tower::Service<Request, Response = Response> + Send + 'static,
+        <L::Service as tower::Service<Request>>::Error: Into<BoxError>,
+        <L::Service as tower::Service<Request>>::Future: Send + 'static,
+    {
+        self.config.layers.push(Box::new(move |transport| {
+            let service = layer.layer(TransportService::new(transport));
+            SharedTransport::new(ServiceTransport::new(service))
+        }));
+        self
+    }
+
     /// Set the target platform.
     ///
+    /// [`DownloadAction::Primary`] downloads the modfile that is live for the platform.
+    ///
     /// See the [mod.io docs](https://docs.mod.io/#targeting-a-platform) for more information.
     pub fn target_platform(mut self, platform: TargetPlatform) -> Builder {
         let name = TargetPlatform::header_name();
         let value = platform.into_header_value();
         self.config.headers.insert(name, value);
+        self.config.target_platform = Some(platform);
         self
     }
 
@@ -267,7 +424,15 @@ pub struct Modio {
 pub(crate) struct ClientRef {
     pub(crate) host: String,
     pub(crate) client: Client,
+    pub(crate) transport: SharedTransport,
+    pub(crate) headers: HeaderMap,
     pub(crate) credentials: Credentials,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) ratelimit: Option<RateLimiter>,
+    pub(crate) on_response: Option<ResponseHook>,
+    pub(crate) target_platform: Option<TargetPlatform>,
+    pub(crate) throttle: Option<Throttle>,
+    pub(crate) virus_scan: Option<VirusScanPolicy>,
 }
 
 impl Modio {
@@ -305,7 +470,15 @@ impl Modio {
             inner: Arc::new(ClientRef {
                 host: self.inner.host.clone(),
                 client: self.inner.client.clone(),
+                transport: self.inner.transport.clone(),
+                headers: self.inner.headers.clone(),
                 credentials: credentials.into(),
+                retry: self.inner.retry.clone(),
+                ratelimit: self.inner.ratelimit.as_ref().map(RateLimiter::fresh),
+                on_response: self.inner.on_response.clone(),
+                target_platform: self.inner.target_platform,
+                throttle: self.inner.throttle.clone(),
+                virus_scan: self.inner.virus_scan,
             }),
         }
     }
@@ -320,10 +493,18 @@ impl Modio {
             inner: Arc::new(ClientRef {
                 host: self.inner.host.clone(),
                 client: self.inner.client.clone(),
+                transport: self.inner.transport.clone(),
+                headers: self.inner.headers.clone(),
                 credentials: Credentials {
                     api_key: self.inner.credentials.api_key.clone(),
                     token: Some(token.into()),
                 },
+                retry: self.inner.retry.clone(),
+                ratelimit: self.inner.ratelimit.as_ref().map(RateLimiter::fresh),
+                on_response: self.inner.on_response.clone(),
+                target_platform: self.inner.target_platform,
+                throttle: self.inner.throttle.clone(),
+                virus_scan: self.inner.virus_scan,
             }),
         }
     }
@@ -1,32 +1,296 @@
 //! Downloading mod files.
 use std::error::Error as StdError;
 use std::fmt;
-use std::path::Path;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use bytes::Bytes;
+use bytes::BytesMut;
 use futures_core::Stream;
-use futures_util::{SinkExt, StreamExt, TryFutureExt, TryStreamExt};
+use futures_util::{pin_mut, TryFutureExt, TryStreamExt};
+use http::header::{CONTENT_RANGE, RANGE};
 use reqwest::{Method, Response, StatusCode};
-use tokio::fs::File as AsyncFile;
-use tokio::io::BufWriter;
-use tokio_util::codec::{BytesCodec, FramedWrite};
-use tracing::debug;
+use tokio::fs::{self, File as AsyncFile, OpenOptions};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tracing::{debug, warn};
+use url::Url;
 
+use crate::cancel::CancelHandle;
 use crate::error::{self, Kind, Result};
-use crate::types::files::File;
+use crate::redact;
+use crate::types::files::{File, PlatformStatus, VirusResult, VirusStatus};
 use crate::types::mods::Mod;
-use crate::Modio;
+use crate::{Modio, TargetPlatform};
+
+mod cache;
+mod chunked;
+mod manager;
+mod progress;
+mod throttle;
+mod verify;
+mod version;
+
+use progress::{Progress, ProgressFn};
+use throttle::Throttled;
+use verify::{Verifier, Verify};
+
+pub use cache::{CacheEntry, DownloadCache};
+pub use manager::{DownloadEvent, DownloadJob, DownloadManager, Events, JobId, JobStatus};
+pub use progress::DownloadProgress;
+pub use semver::VersionReq;
+pub use throttle::Throttle;
 
 /// A `Downloader` can be used to stream a mod file or save the file to a local file.
 /// Constructed with [`Modio::download`].
 pub struct Downloader {
     modio: Modio,
     action: DownloadAction,
+    resume: bool,
+    verify: bool,
+    chunks: usize,
+    progress: Option<ProgressFn>,
+    cancel: Option<CancelHandle>,
+    cache: Option<DownloadCache>,
+    throttle: Option<Throttle>,
+    virus_scan: Option<VirusScanPolicy>,
+    resolved: Option<File>,
 }
 
 impl Downloader {
     pub(crate) fn new(modio: Modio, action: DownloadAction) -> Self {
-        Self { modio, action }
+        Self {
+            modio,
+            action,
+            resume: false,
+            verify: true,
+            chunks: 1,
+            progress: None,
+            cancel: None,
+            cache: None,
+            throttle: None,
+            virus_scan: None,
+            resolved: None,
+        }
+    }
+
+    /// Resolve the [`DownloadAction`] to the modfile without starting the transfer.
+    ///
+    /// The returned [`ResolvedDownload`] exposes the metadata of the modfile, e.g. its version
+    /// and size, and starts the transfer without requesting the modfile again.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// #     let modio = modio::Modio::new("api-key")?;
+    /// let action = modio::DownloadAction::Primary {
+    ///     game_id: 5,
+    ///     mod_id: 19,
+    /// };
+    ///
+    /// let download = modio.download(action).resolve().await?;
+    /// let file = download.file();
+    /// println!(
+    ///     "Downloading {} ({} bytes)",
+    ///     file.version.as_deref().unwrap_or("unknown version"),
+    ///     file.filesize,
+    /// );
+    /// download.save_to_file("mod.zip").await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub async fn resolve(self) -> Result<ResolvedDownload> {
+        let file = self.resolved_file().await?;
+        Ok(ResolvedDownload {
+            file,
+            downloader: self,
+        })
+    }
+
+    async fn resolved_file(&self) -> Result<File> {
+        let file = match self.resolved {
+            Some(ref file) => file.clone(),
+            None => resolve(&self.modio, &self.action).await?,
+        };
+        let policy = self
+            .virus_scan
+            .or(self.modio.inner.virus_scan)
+            .unwrap_or_default();
+        policy.check(&file)?;
+        Ok(file)
+    }
+
+    /// Resume a previous download of [`save_to_file`](Downloader::save_to_file).
+    ///
+    /// If the local file already exists, only the remaining bytes are requested with a `Range`
+    /// header and appended to the file. The file is written from the start if the server
+    /// doesn't honour the range request. An expired download url is resolved again.
+    ///
+    /// A local file with the size of the modfile is kept as complete download. With
+    /// [`verify`](Downloader::verify) enabled, its MD5 hash is checked and the file is
+    /// downloaded again if it doesn't match.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// #     let modio = modio::Modio::new("api-key")?;
+    /// let action = modio::DownloadAction::Primary {
+    ///     game_id: 5,
+    ///     mod_id: 19,
+    /// };
+    ///
+    /// modio.download(action).resume(true).save_to_file("mod.zip").await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn resume(self, resume: bool) -> Self {
+        Self { resume, ..self }
+    }
+
+    /// Verify the size and the MD5 hash of the downloaded file with the metadata of the modfile.
+    ///
+    /// The download fails with [`Error::SizeMismatch`] or [`Error::HashMismatch`] as source
+    /// error. A local file of [`save_to_file`](Downloader::save_to_file) is deleted if the
+    /// verification fails.
+    ///
+    /// Defaults to `true`.
+    pub fn verify(self, verify: bool) -> Self {
+        Self { verify, ..self }
+    }
+
+    /// Download the file of [`save_to_file`](Downloader::save_to_file) with up to `chunks`
+    /// concurrent range requests.
+    ///
+    /// The file is split into byte ranges of at least 1 MiB which are written into a
+    /// preallocated file. Failed ranges are retried on their own. The file is downloaded as a
+    /// single stream if the server doesn't support range requests or a partially downloaded
+    /// file is [resumed](Downloader::resume).
+    ///
+    /// A parallel download can't be resumed. The preallocated file is removed if the download
+    /// fails or is [cancelled](Downloader::cancel_handle).
+    ///
+    /// Defaults to `1`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// #     let modio = modio::Modio::new("api-key")?;
+    /// let action = modio::DownloadAction::Primary {
+    ///     game_id: 5,
+    ///     mod_id: 19,
+    /// };
+    ///
+    /// modio.download(action).parallel(4).save_to_file("mod.zip").await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn parallel(self, chunks: usize) -> Self {
+        Self {
+            chunks: chunks.max(1),
+            ..self
+        }
+    }
+
+    /// Register a callback that is called with the [`DownloadProgress`] for every received chunk.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// #     let modio = modio::Modio::new("api-key")?;
+    /// let action = modio::DownloadAction::Primary {
+    ///     game_id: 5,
+    ///     mod_id: 19,
+    /// };
+    ///
+    /// modio
+    ///     .download(action)
+    ///     .progress(|p| match p.total {
+    ///         Some(total) => println!("{}/{} bytes, {:.0} B/s", p.downloaded, total, p.speed),
+    ///         None => println!("{} bytes, {:.0} B/s", p.downloaded, p.speed),
+    ///     })
+    ///     .save_to_file("mod.zip")
+    ///     .await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn progress<F>(self, f: F) -> Self
+    where
+        F: Fn(&DownloadProgress) + Send + Sync + 'static,
+    {
+        Self {
+            progress: Some(ProgressFn::new(f)),
+            ..self
+        }
+    }
+
+    /// Stop the download once the [`CancelHandle`] is cancelled.
+    ///
+    /// The download fails with an error for which [`Error::is_cancelled`] returns true.
+    /// The bytes already written by [`save_to_file`](Downloader::save_to_file) are kept,
+    /// so the download can be continued with [`resume`](Downloader::resume). The file of a
+    /// [parallel](Downloader::parallel) download is removed instead.
+    ///
+    /// [`Error::is_cancelled`]: crate::Error::is_cancelled
+    pub fn cancel_handle(self, cancel: CancelHandle) -> Self {
+        Self {
+            cancel: Some(cancel),
+            ..self
+        }
+    }
+
+    /// Limit the bandwidth of the download with the [`Throttle`].
+    ///
+    /// The limit applies in addition to the throttle of the client set with
+    /// [`Builder::throttle`](crate::Builder::throttle).
+    pub fn throttle(self, throttle: Throttle) -> Self {
+        Self {
+            throttle: Some(throttle),
+            ..self
+        }
+    }
+
+    /// Set the [`VirusScanPolicy`] for the modfile of the download.
+    ///
+    /// Overrides the policy of the client set with
+    /// [`Builder::virus_scan_policy`](crate::Builder::virus_scan_policy).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use modio::download::VirusScanPolicy;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// #     let modio = modio::Modio::new("api-key")?;
+    ///
+    /// modio
+    ///     .download((5, 19))
+    ///     .virus_scan_policy(VirusScanPolicy::RequireScanned)
+    ///     .save_to_file("mod.zip")
+    ///     .await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn virus_scan_policy(self, policy: VirusScanPolicy) -> Self {
+        Self {
+            virus_scan: Some(policy),
+            ..self
+        }
+    }
+
+    /// Returns the throttles of the client and the download.
+    fn throttles(&self) -> Vec<Throttle> {
+        let client = self.modio.inner.throttle.as_ref();
+        client.into_iter().chain(&self.throttle).cloned().collect()
+    }
+
+    /// Restore the mod file from the [`DownloadCache`] and add downloaded files to the cache.
+    ///
+    /// Applies to [`save_to_file`](Downloader::save_to_file),
+    /// [`save_to_dir`](Downloader::save_to_dir) and `extract_to`. Errors of the cache are
+    /// logged and don't fail the download.
+    pub fn cache(self, cache: DownloadCache) -> Self {
+        Self {
+            cache: Some(cache),
+            ..self
+        }
     }
 
     /// Save the mod file to a local file.
@@ -45,11 +309,209 @@ impl Downloader {
     /// # }
     /// ```
     pub async fn save_to_file<P: AsRef<Path>>(self, file: P) -> Result<()> {
-        let out = AsyncFile::create(file).map_err(error::decode).await?;
-        let out = BufWriter::with_capacity(512 * 512, out);
-        let out = FramedWrite::new(out, BytesCodec::new());
-        let out = SinkExt::<Bytes>::sink_map_err(out, error::decode);
-        self.stream().forward(out).await
+        let resolved = self.resolved_file().await?;
+        self.save(file.as_ref(), resolved).await
+    }
+
+    /// Save the mod file into the directory `dir` with the filename of the modfile.
+    ///
+    /// The filename is sanitized to prevent path traversal. The file is written to a temporary
+    /// file in the same directory, synced to disk and renamed only after the download and the
+    /// verification have succeeded. A [resumed](Downloader::resume) download continues with the
+    /// temporary file of a previous attempt.
+    ///
+    /// Returns the path of the saved file and the resolved modfile.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// #     let modio = modio::Modio::new("api-key")?;
+    /// let action = modio::DownloadAction::Primary {
+    ///     game_id: 5,
+    ///     mod_id: 19,
+    /// };
+    ///
+    /// let (path, file) = modio.download(action).save_to_dir("mods").await?;
+    /// println!("saved {:?} to {}", file.version, path.display());
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub async fn save_to_dir<P: AsRef<Path>>(self, dir: P) -> Result<(PathBuf, File)> {
+        let file = self.resolved_file().await?;
+
+        let filename = sanitize_filename(&file.filename, file.id);
+        let path = dir.as_ref().join(&filename);
+        let tmp = dir.as_ref().join(format!(".{}.part", filename));
+
+        self.save(&tmp, file.clone()).await?;
+
+        let out = AsyncFile::open(&tmp).await.map_err(error::decode)?;
+        out.sync_all().await.map_err(error::decode)?;
+        drop(out);
+        fs::rename(&tmp, &path).await.map_err(error::decode)?;
+
+        Ok((path, file))
+    }
+
+    /// Download the mod file and extract it with the [`Extractor`](crate::extract::Extractor).
+    ///
+    /// The archive is saved to a temporary file next to the target directory of the extractor
+    /// and removed after the extraction. Returns the resolved modfile.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use modio::extract::Extractor;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// #     let modio = modio::Modio::new("api-key")?;
+    /// let action = modio::DownloadAction::Primary {
+    ///     game_id: 5,
+    ///     mod_id: 19,
+    /// };
+    ///
+    /// let extractor = Extractor::new("mods/my-mod");
+    /// let file = modio.download(action).extract_to(&extractor).await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "extract")]
+    pub async fn extract_to(self, extractor: &crate::extract::Extractor) -> Result<File> {
+        let file = self.resolved_file().await?;
+
+        let dir = extractor.dir();
+        let filename = sanitize_filename(&file.filename, file.id);
+        let tmp = match dir.parent() {
+            Some(parent) => parent.join(format!(".{}.part", filename)),
+            None => PathBuf::from(format!(".{}.part", filename)),
+        };
+        if let Some(parent) = tmp.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).await.map_err(error::decode)?;
+        }
+
+        self.save(&tmp, file.clone()).await?;
+        let result = extractor.extract_file(&tmp).await;
+        fs::remove_file(&tmp).await.map_err(error::decode)?;
+        result.map(|()| file)
+    }
+
+    async fn save(&self, path: &Path, file: File) -> Result<()> {
+        let cache = match self.cache {
+            Some(ref cache) => cache,
+            None => return self.transfer(path, file).await,
+        };
+        match cache.restore(&file, path).await {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            Err(e) => debug!("failed to restore cached file: {}", e),
+        }
+        self.transfer(path, file.clone()).await?;
+        if let Err(e) = cache.insert(&file, path).await {
+            debug!("failed to cache file {}: {}", file.id, e);
+        }
+        Ok(())
+    }
+
+    async fn transfer(&self, path: &Path, file: File) -> Result<()> {
+        let mut file = self.refresh_expired(file).await?;
+        let offset = if self.resume {
+            match fs::metadata(path).await {
+                Ok(m) => m.len(),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+                Err(e) => return Err(error::decode(e)),
+            }
+        } else {
+            0
+        };
+
+        let offset = if offset > 0 && offset == file.filesize {
+            if !self.verify {
+                debug!("file already downloaded: {}", path.display());
+                return Ok(());
+            }
+            // A file of the same size may be left over from another version of the mod.
+            let mut verifier = Verifier::new(&file);
+            verifier.seed(path, offset).await?;
+            if verifier.finish().is_ok() {
+                debug!("file already downloaded: {}", path.display());
+                return Ok(());
+            }
+            debug!(
+                "existing file doesn't match, downloading again: {}",
+                path.display()
+            );
+            0
+        } else if offset > file.filesize {
+            0
+        } else {
+            offset
+        };
+        let mut verifier = if self.verify {
+            Some(Verifier::new(&file))
+        } else {
+            None
+        };
+        let filesize = file.filesize;
+
+        let (resp, offset) = if offset == 0 && chunked::count(filesize, self.chunks) > 1 {
+            let mut resolved = false;
+            loop {
+                let url = &file.download.binary_url;
+                match chunked::download(self, url, filesize, path).await {
+                    Ok(Some(resp)) if is_expired_status(resp.status()) && !resolved => {
+                        debug!("download url expired, resolving the file again");
+                        file = resolve_again(&self.modio, &self.action, &file).await?;
+                        resolved = true;
+                    }
+                    Ok(Some(resp)) => {
+                        debug!("range requests not supported, downloading as single stream");
+                        let resp = resp
+                            .error_for_status()
+                            .map_err(|e| error::request(redact::error(e)))?;
+                        break (resp, 0);
+                    }
+                    Ok(None) => return verify_file(path, verifier).await,
+                    Err(e) => {
+                        // The ranges of the preallocated file are written out of order, so
+                        // the file can't be resumed, even after a cancellation.
+                        let _ = fs::remove_file(path).await;
+                        return Err(e);
+                    }
+                }
+            }
+        } else {
+            request_range(&self.modio, &self.action, file, offset).await?
+        };
+        if let (Some(verifier), true) = (&mut verifier, offset > 0) {
+            verifier.seed(path, offset).await?;
+        }
+        let stream = self.body_stream(resp, offset, filesize, verifier);
+
+        let out = if offset > 0 {
+            debug!("resuming download at byte {}", offset);
+            OpenOptions::new().append(true).open(path).await
+        } else {
+            AsyncFile::create(path).await
+        };
+        let out = out.map_err(error::decode)?;
+        let mut out = BufWriter::with_capacity(512 * 512, out);
+
+        pin_mut!(stream);
+        let result: Result<()> = async {
+            while let Some(bytes) = stream.try_next().await? {
+                out.write_all(&bytes).await.map_err(error::decode)?;
+            }
+            Ok(())
+        }
+        .await;
+        // Flush the written bytes of failed or cancelled downloads to be able to resume them.
+        out.flush().await.map_err(error::decode)?;
+
+        if let Err(ref e) = result {
+            if e.is_download() {
+                debug!("verification failed, removing file: {}", path.display());
+                fs::remove_file(path).await.map_err(error::decode)?;
+            }
+        }
+        result
     }
 
     /// Get the full mod file as `Bytes`.
@@ -68,8 +530,14 @@ impl Downloader {
     /// # }
     /// ```
     pub async fn bytes(self) -> Result<Bytes> {
-        let resp = request_file(self.modio, self.action).await?;
-        resp.bytes().map_err(error::request).await
+        let stream = self.stream();
+        pin_mut!(stream);
+
+        let mut buf = BytesMut::new();
+        while let Some(bytes) = stream.try_next().await? {
+            buf.extend_from_slice(&bytes);
+        }
+        Ok(buf.freeze())
     }
 
     /// `Stream` of bytes of the mod file.
@@ -93,14 +561,269 @@ impl Downloader {
     /// # }
     /// ```
     pub fn stream(self) -> impl Stream<Item = Result<Bytes>> {
-        request_file(self.modio, self.action)
-            .and_then(|res| async { Ok(res.bytes_stream().map_err(error::request)) })
-            .try_flatten_stream()
+        let stream = async move {
+            let file = self.resolved_file().await?;
+            let file = self.refresh_expired(file).await?;
+            let (res, _) = request_range(&self.modio, &self.action, file.clone(), 0).await?;
+            let verifier = if self.verify {
+                Some(Verifier::new(&file))
+            } else {
+                None
+            };
+            Ok(self.body_stream(res, 0, file.filesize, verifier))
+        };
+        stream.try_flatten_stream()
+    }
+
+    /// Resolve the file again if its download url has expired or is about to expire.
+    async fn refresh_expired(&self, file: File) -> Result<File> {
+        if !is_expired(&file) {
+            return Ok(file);
+        }
+        debug!(
+            "download url of file {} expired, resolving the file again",
+            file.id
+        );
+        resolve_again(&self.modio, &self.action, &file).await
+    }
+
+    /// Returns the stream of the response body with progress reporting, cancellation and
+    /// verification applied.
+    fn body_stream(
+        &self,
+        resp: Response,
+        offset: u64,
+        filesize: u64,
+        verifier: Option<Verifier>,
+    ) -> impl Stream<Item = Result<Bytes>> {
+        let total = resp.content_length().map(|len| offset + len);
+        let stream = resp
+            .bytes_stream()
+            .map_err(|e| error::request(redact::error(e)));
+        let stream = Throttled::new(stream, self.throttles());
+        let stream = Progress::new(
+            stream,
+            self.progress.clone(),
+            self.cancel.clone(),
+            offset,
+            total.or(Some(filesize)),
+        );
+        Verify::new(stream, verifier)
+    }
+}
+
+/// A download with the resolved modfile, constructed with [`Downloader::resolve`].
+pub struct ResolvedDownload {
+    file: File,
+    downloader: Downloader,
+}
+
+impl ResolvedDownload {
+    /// Returns the resolved modfile.
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// Save the mod file to a local file. See [`Downloader::save_to_file`].
+    pub async fn save_to_file<P: AsRef<Path>>(self, file: P) -> Result<()> {
+        self.into_downloader().save_to_file(file).await
+    }
+
+    /// Save the mod file into a directory. See [`Downloader::save_to_dir`].
+    pub async fn save_to_dir<P: AsRef<Path>>(self, dir: P) -> Result<(PathBuf, File)> {
+        self.into_downloader().save_to_dir(dir).await
+    }
+
+    /// Get the full mod file as `Bytes`. See [`Downloader::bytes`].
+    pub async fn bytes(self) -> Result<Bytes> {
+        self.into_downloader().bytes().await
+    }
+
+    /// `Stream` of bytes of the mod file. See [`Downloader::stream`].
+    pub fn stream(self) -> impl Stream<Item = Result<Bytes>> {
+        self.into_downloader().stream()
+    }
+
+    fn into_downloader(self) -> Downloader {
+        Downloader {
+            resolved: Some(self.file),
+            ..self.downloader
+        }
+    }
+}
+
+/// Returns the last component of the filename with characters that are not allowed in
+/// filenames replaced. Falls back to `modfile-<id>` if nothing is left.
+fn sanitize_filename(filename: &str, file_id: u32) -> String {
+    let name = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    let name = name.trim().trim_end_matches('.');
+
+    const RESERVED: &[&str] = &[
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+    let stem = name.split('.').next().unwrap_or_default();
+
+    if name.is_empty() {
+        format!("modfile-{}", file_id)
+    } else if RESERVED.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        format!("_{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Verify a completely written file and remove it if the verification fails.
+async fn verify_file(path: &Path, verifier: Option<Verifier>) -> Result<()> {
+    let mut verifier = match verifier {
+        Some(verifier) => verifier,
+        None => return Ok(()),
+    };
+    let len = fs::metadata(path).await.map_err(error::decode)?.len();
+    verifier.seed(path, len).await?;
+    if let Err(e) = verifier.finish() {
+        debug!("verification failed, removing file: {}", path.display());
+        fs::remove_file(path).await.map_err(error::decode)?;
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Request the file starting at `offset` and return the response with the actual offset of its
+/// body, which is `0` if the server ignored the range.
+async fn request_range(
+    modio: &Modio,
+    action: &DownloadAction,
+    mut file: File,
+    offset: u64,
+) -> Result<(Response, u64)> {
+    let mut resolved = false;
+    loop {
+        let resp = fetch(modio, file.download.binary_url.clone(), offset, None).await?;
+        match resp.status() {
+            StatusCode::PARTIAL_CONTENT if offset > 0 => {
+                let start = resp
+                    .headers()
+                    .get(CONTENT_RANGE)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(parse_content_range_start);
+                if start == Some(offset) {
+                    return Ok((resp, offset));
+                }
+                debug!("unexpected content range, downloading the whole file");
+            }
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                debug!("range not satisfiable, downloading the whole file");
+            }
+            status if is_expired_status(status) && !resolved => {
+                debug!("download url expired, resolving the file again");
+                file = resolve_again(modio, action, &file).await?;
+                resolved = true;
+                continue;
+            }
+            _ => {
+                let resp = resp
+                    .error_for_status()
+                    .map_err(|e| error::request(redact::error(e)))?;
+                return Ok((resp, 0));
+            }
+        }
+        let resp = fetch(modio, file.download.binary_url, 0, None)
+            .await?
+            .error_for_status()
+            .map_err(|e| error::request(redact::error(e)))?;
+        return Ok((resp, 0));
+    }
+}
+
+async fn fetch(modio: &Modio, url: Url, start: u64, end: Option<u64>) -> Result<Response> {
+    debug!("downloading file: {}", redact::url(&url));
+    let mut req = modio
+        .inner
+        .client
+        .request(Method::GET, url)
+        .headers(modio.inner.headers.clone());
+    match end {
+        Some(end) => req = req.header(RANGE, format!("bytes={}-{}", start, end)),
+        None if start > 0 => req = req.header(RANGE, format!("bytes={}-", start)),
+        None => {}
+    }
+    let req = req.build().map_err(|e| error::builder(redact::error(e)))?;
+    modio
+        .inner
+        .transport
+        .send(req)
+        .map_err(|e| error::request(redact::boxed_error(e)))
+        .await
+}
+
+/// Parse the first byte position of a `Content-Range: bytes <start>-<end>/<size>` header.
+fn parse_content_range_start(value: &str) -> Option<u64> {
+    let range = value.strip_prefix("bytes ")?;
+    let (start, _) = range.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+/// Download urls are resolved again if they expire within this many seconds.
+const EXPIRY_MARGIN: u64 = 60;
+
+/// Returns `true` if the download url of the file has expired or expires within
+/// [`EXPIRY_MARGIN`]. A `date_expires` of `0` is treated as unknown.
+fn is_expired(file: &File) -> bool {
+    let expires = file.download.date_expires;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    expires > 0 && expires <= now + EXPIRY_MARGIN
+}
+
+/// Returns `true` if the status of a download response indicates an expired download url.
+fn is_expired_status(status: StatusCode) -> bool {
+    matches!(status, StatusCode::FORBIDDEN | StatusCode::GONE)
+}
+
+/// Resolve the file again to get a new download url.
+///
+/// A `DownloadAction::FileObj` is refreshed with the game id from the path of its download url.
+/// The download fails with [`Error::UrlExpired`] if the url has no game id.
+async fn resolve_again(modio: &Modio, action: &DownloadAction, file: &File) -> Result<File> {
+    if let DownloadAction::FileObj(_) = action {
+        let game_id = file.download.binary_url.path_segments().and_then(|mut s| {
+            s.by_ref().find(|s| *s == "games")?;
+            s.next()?.parse().ok()
+        });
+        return match game_id {
+            Some(game_id) => {
+                let fileref = modio.mod_(game_id, file.mod_id).file(file.id);
+                fileref
+                    .get()
+                    .map_err(|e| match e.kind() {
+                        Kind::Status(StatusCode::NOT_FOUND) => {
+                            error::download_file_not_found(game_id, file.mod_id, file.id)
+                        }
+                        _ => e,
+                    })
+                    .await
+            }
+            None => Err(error::download_url_expired(file.mod_id, file.id)),
+        };
     }
+    resolve(modio, action).await
 }
 
-async fn request_file(modio: Modio, action: DownloadAction) -> Result<Response> {
-    let url = match action {
+async fn resolve(modio: &Modio, action: &DownloadAction) -> Result<File> {
+    let file = match *action {
         DownloadAction::Primary { game_id, mod_id } => {
             let modref = modio.mod_(game_id, mod_id);
             let m = modref
@@ -112,20 +835,25 @@ async fn request_file(modio: Modio, action: DownloadAction) -> Result<Response>
                     _ => e,
                 })
                 .await?;
-            if let Some(file) = m.modfile {
-                file.download.binary_url
-            } else {
-                return Err(error::download_no_primary(game_id, mod_id));
+            match modio.inner.target_platform {
+                // Games without cross-platform support list no platforms for their mods.
+                Some(platform) if !m.platforms.is_empty() => {
+                    platform_file(modio, m, platform).await?
+                }
+                _ => match m.modfile {
+                    Some(file) => file,
+                    None => return Err(error::download_no_primary(game_id, mod_id)),
+                },
             }
         }
-        DownloadAction::FileObj(file) => file.download.binary_url,
+        DownloadAction::FileObj(ref file) => File::clone(file),
         DownloadAction::File {
             game_id,
             mod_id,
             file_id,
         } => {
             let fileref = modio.mod_(game_id, mod_id).file(file_id);
-            let file = fileref
+            fileref
                 .get()
                 .map_err(|e| match e.kind() {
                     Kind::Status(StatusCode::NOT_FOUND) => {
@@ -133,14 +861,13 @@ async fn request_file(modio: Modio, action: DownloadAction) -> Result<Response>
                     }
                     _ => e,
                 })
-                .await?;
-            file.download.binary_url
+                .await?
         }
         DownloadAction::Version {
             game_id,
             mod_id,
-            version,
-            policy,
+            ref version,
+            ref policy,
         } => {
             use crate::files::filters::{DateAdded, Version};
             use crate::filter::prelude::*;
@@ -165,39 +892,95 @@ async fn request_file(modio: Modio, action: DownloadAction) -> Result<Response>
             let (file, error) = match (list.len(), policy) {
                 (0, _) => (
                     None,
-                    Some(error::download_version_not_found(game_id, mod_id, version)),
+                    Some(error::download_version_not_found(
+                        game_id,
+                        mod_id,
+                        version.clone(),
+                    )),
                 ),
-                (1, _) | (_, Latest) => (Some(list.remove(0)), None),
+                (1, _) | (_, Latest | Prerelease | FailOnInvalid) => (Some(list.remove(0)), None),
                 (_, Fail) => (
                     None,
-                    Some(error::download_multiple_files(game_id, mod_id, version)),
+                    Some(error::download_multiple_files(
+                        game_id,
+                        mod_id,
+                        version.clone(),
+                    )),
                 ),
             };
 
             if let Some(file) = file {
-                file.download.binary_url
+                file
             } else {
                 return Err(error.expect("bug in previous match!"));
             }
         }
+        DownloadAction::VersionReq {
+            game_id,
+            mod_id,
+            ref req,
+            ref policy,
+        } => {
+            use crate::files::filters::DateAdded;
+            use crate::filter::prelude::*;
+
+            let files = modio.mod_(game_id, mod_id).files();
+            let list = files
+                .search(DateAdded::desc())
+                .collect()
+                .map_err(|e| match e.kind() {
+                    Kind::Status(StatusCode::NOT_FOUND) => {
+                        error::download_mod_not_found(game_id, mod_id)
+                    }
+                    _ => e,
+                })
+                .await?;
+            version::select(game_id, mod_id, req, policy, list)?
+        }
     };
+    Ok(file)
+}
 
-    debug!("downloading file: {}", url);
-    modio
-        .inner
-        .client
-        .request(Method::GET, url)
-        .send()
-        .map_err(error::builder_or_request)
-        .await?
-        .error_for_status()
-        .map_err(error::request)
+/// Returns the modfile that is live and approved for the target platform.
+async fn platform_file(modio: &Modio, m: Mod, platform: TargetPlatform) -> Result<File> {
+    let (game_id, mod_id) = (m.game_id, m.id);
+    let file_id = match m.platforms.iter().find(|p| p.target == platform) {
+        Some(p) => p.modfile_id,
+        None => return Err(error::download_no_platform_file(game_id, mod_id, platform)),
+    };
+    let file = match m.modfile {
+        Some(file) if file.id == file_id => file,
+        _ => {
+            let fileref = modio.mod_(game_id, mod_id).file(file_id);
+            fileref
+                .get()
+                .map_err(|e| match e.kind() {
+                    Kind::Status(StatusCode::NOT_FOUND) => {
+                        error::download_file_not_found(game_id, mod_id, file_id)
+                    }
+                    _ => e,
+                })
+                .await?
+        }
+    };
+    let approved = file
+        .platforms
+        .iter()
+        .any(|p| p.target == platform && matches!(p.status, PlatformStatus::Approved));
+    if !approved {
+        debug!("file {} is not approved for {}", file.id, platform);
+        return Err(error::download_no_platform_file(game_id, mod_id, platform));
+    }
+    Ok(file)
 }
 
 /// Defines the action that is performed for [`Modio::download`].
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum DownloadAction {
     /// Download the primary modfile of a mod.
+    ///
+    /// If the client has a [target platform](crate::Builder::target_platform), the modfile
+    /// that is live and approved for the platform is downloaded instead.
     Primary { game_id: u32, mod_id: u32 },
     /// Download a specific modfile of a mod.
     File {
@@ -206,6 +989,9 @@ pub enum DownloadAction {
         file_id: u32,
     },
     /// Download a specific modfile.
+    ///
+    /// The modfile is fetched again with [`FileRef::get`](crate::files::FileRef::get) if its
+    /// download url has expired or the download is refused with `403 Forbidden` or `410 Gone`.
     FileObj(Box<File>),
     /// Download a specific version of a mod.
     Version {
@@ -214,15 +1000,86 @@ pub enum DownloadAction {
         version: String,
         policy: ResolvePolicy,
     },
+    /// Download the file with the highest version matching a semver requirement.
+    ///
+    /// The versions of the files are parsed leniently, e.g. `v1.4` is parsed as `1.4.0`. Files
+    /// without a version or with an unparseable version are skipped unless the policy is
+    /// [`ResolvePolicy::FailOnInvalid`].
+    VersionReq {
+        game_id: u32,
+        mod_id: u32,
+        req: VersionReq,
+        policy: ResolvePolicy,
+    },
 }
 
-/// Defines the policy for `DownloadAction::Version` when multiple files are found.
-#[derive(Debug)]
+/// Defines the policy for `DownloadAction::Version` and `DownloadAction::VersionReq` when
+/// multiple files are found.
+#[derive(Clone, Debug)]
 pub enum ResolvePolicy {
     /// Download the latest file.
     Latest,
     /// Return with [`Error::MultipleFilesFound`] as source error.
     Fail,
+    /// Download the latest file. [`DownloadAction::VersionReq`] also matches prereleases,
+    /// ordered below their release, e.g. `1.5.0-beta` for `^1.4` but not `1.4.0-beta`.
+    Prerelease,
+    /// Download the latest file. [`DownloadAction::VersionReq`] returns with
+    /// [`Error::InvalidVersion`] as source error if the version of a file can't be parsed.
+    FailOnInvalid,
+}
+
+/// Defines how the [virus scan](crate::files::VirusScan) of a modfile is handled before it's
+/// downloaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VirusScanPolicy {
+    /// Download the file regardless of its virus scan.
+    Allow,
+    /// Log a warning if the file is not scanned yet or flagged, and download it anyway.
+    Warn,
+    /// Refuse files flagged as malicious or potentially harmful with
+    /// [`Error::VirusScan`] as source error.
+    RefuseFlagged,
+    /// Refuse flagged files and files whose scan has not completed with [`Error::VirusScan`]
+    /// as source error.
+    RequireScanned,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for VirusScanPolicy {
+    fn default() -> VirusScanPolicy {
+        VirusScanPolicy::Allow
+    }
+}
+
+impl VirusScanPolicy {
+    fn check(self, file: &File) -> Result<()> {
+        let scan = &file.virus_scan;
+        let refuse = match self {
+            VirusScanPolicy::Allow => false,
+            VirusScanPolicy::Warn => {
+                if scan.is_flagged() || !scan.is_scanned() {
+                    warn!(
+                        "file {} of mod {}: virus scan status {:?}, result {:?}",
+                        file.id, file.mod_id, scan.status, scan.result,
+                    );
+                }
+                false
+            }
+            VirusScanPolicy::RefuseFlagged => scan.is_flagged(),
+            VirusScanPolicy::RequireScanned => scan.is_flagged() || !scan.is_scanned(),
+        };
+        if refuse {
+            return Err(error::download_virus_scan(
+                file.mod_id,
+                file.id,
+                scan.status,
+                scan.result,
+            ));
+        }
+        Ok(())
+    }
 }
 
 /// The Errors that may occur when using [`Modio::download`].
@@ -232,6 +1089,12 @@ pub enum Error {
     ModNotFound { game_id: u32, mod_id: u32 },
     /// The mod has no primary file.
     NoPrimaryFile { game_id: u32, mod_id: u32 },
+    /// The mod has no live file that is approved for the target platform.
+    NoPlatformFile {
+        game_id: u32,
+        mod_id: u32,
+        platform: TargetPlatform,
+    },
     /// The specific file of a mod was not found.
     FileNotFound {
         game_id: u32,
@@ -251,6 +1114,37 @@ pub enum Error {
         mod_id: u32,
         version: String,
     },
+    /// The version of a file can't be parsed as semver and the policy was set to
+    /// [`ResolvePolicy::FailOnInvalid`].
+    InvalidVersion {
+        game_id: u32,
+        mod_id: u32,
+        file_id: u32,
+        version: String,
+    },
+    /// The file was refused by the [`VirusScanPolicy`] because it's not scanned yet or was
+    /// flagged by the virus scan.
+    VirusScan {
+        mod_id: u32,
+        file_id: u32,
+        status: VirusStatus,
+        result: VirusResult,
+    },
+    /// The download url of the file has expired and the file can't be resolved again because
+    /// the url doesn't contain the game id.
+    UrlExpired { mod_id: u32, file_id: u32 },
+    /// The size of the downloaded file doesn't match the size of the modfile.
+    SizeMismatch {
+        file_id: u32,
+        expected: u64,
+        actual: u64,
+    },
+    /// The MD5 hash of the downloaded file doesn't match the hash of the modfile.
+    HashMismatch {
+        file_id: u32,
+        expected: String,
+        actual: String,
+    },
 }
 
 impl StdError for Error {}
@@ -286,6 +1180,15 @@ impl fmt::Display for Error {
                 "Mod {{id: {1}, game_id: {0}}} Mod has no primary file.",
                 game_id, mod_id,
             ),
+            Error::NoPlatformFile {
+                game_id,
+                mod_id,
+                platform,
+            } => write!(
+                fmt,
+                "Mod {{id: {1}, game_id: {0}}}: No approved file for platform '{2}'.",
+                game_id, mod_id, platform,
+            ),
             Error::VersionNotFound {
                 game_id,
                 mod_id,
@@ -295,6 +1198,49 @@ impl fmt::Display for Error {
                 "Mod {{id: {1}, game_id: {0}}}: No file with version '{2}' found.",
                 game_id, mod_id, version,
             ),
+            Error::InvalidVersion {
+                game_id,
+                mod_id,
+                file_id,
+                version,
+            } => write!(
+                fmt,
+                "Mod {{id: {1}, game_id: {0}}}: File {{ id: {2} }} has an invalid version '{3}'.",
+                game_id, mod_id, file_id, version,
+            ),
+            Error::VirusScan {
+                mod_id,
+                file_id,
+                status,
+                result,
+            } => write!(
+                fmt,
+                "Mod {{id: {0}}}: File {{ id: {1} }} refused, virus scan status: {2:?}, result: {3:?}.",
+                mod_id, file_id, status, result,
+            ),
+            Error::UrlExpired { mod_id, file_id } => write!(
+                fmt,
+                "Mod {{id: {0}}}: File {{ id: {1} }} has an expired download url that can't be renewed.",
+                mod_id, file_id,
+            ),
+            Error::SizeMismatch {
+                file_id,
+                expected,
+                actual,
+            } => write!(
+                fmt,
+                "File {{id: {0}}}: size mismatch, expected {1} bytes, got {2} bytes.",
+                file_id, expected, actual,
+            ),
+            Error::HashMismatch {
+                file_id,
+                expected,
+                actual,
+            } => write!(
+                fmt,
+                "File {{id: {0}}}: hash mismatch, expected md5 '{1}', got '{2}'.",
+                file_id, expected, actual,
+            ),
         }
     }
 }
@@ -363,3 +1309,32 @@ impl<'a> From<(u32, u32, &'a str)> for DownloadAction {
         }
     }
 }
+
+/// Convert `(u32, u32, VersionReq)` to [`DownloadAction::VersionReq`] with resolve policy
+/// set to `ResolvePolicy::Latest`
+impl From<(u32, u32, VersionReq)> for DownloadAction {
+    fn from((game_id, mod_id, req): (u32, u32, VersionReq)) -> DownloadAction {
+        DownloadAction::VersionReq {
+            game_id,
+            mod_id,
+            req,
+            policy: ResolvePolicy::Latest,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize_filename;
+
+    #[test]
+    fn sanitized_filenames() {
+        assert_eq!(sanitize_filename("mod.zip", 1), "mod.zip");
+        assert_eq!(sanitize_filename("../../etc/passwd", 1), "passwd");
+        assert_eq!(sanitize_filename("..\\..\\mod.zip", 1), "mod.zip");
+        assert_eq!(sanitize_filename("a:b*c?.zip", 1), "a_b_c_.zip");
+        assert_eq!(sanitize_filename("..", 1), "modfile-1");
+        assert_eq!(sanitize_filename("", 2), "modfile-2");
+        assert_eq!(sanitize_filename("nul.zip", 1), "_nul.zip");
+    }
+}
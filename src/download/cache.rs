@@ -0,0 +1,378 @@
+//! Content-addressed cache of downloaded modfiles.
+use std::cmp::Reverse;
+use std::fs::Metadata;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::fs;
+use tracing::debug;
+
+use super::verify::Verifier;
+use crate::error::{self, Result};
+use crate::types::files::File;
+
+/// A local cache of verified modfiles stored under their MD5 hash.
+///
+/// Downloads with [`Downloader::cache`] restore a modfile from the cache instead of
+/// downloading it again if the cache has a file with the same MD5 hash and size. Successful
+/// downloads are added to the cache after their hash has been checked.
+///
+/// The cache is limited to [`max_size`](DownloadCache::max_size) bytes. The least recently
+/// used files are removed once the limit is exceeded. The last use of a file is recorded in a
+/// separate `.{md5}.used` file, so the cached files and their hardlinked copies are left as is.
+///
+/// # Example
+/// ```no_run
+/// use modio::download::DownloadCache;
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// #     let modio = modio::Modio::new("api-key")?;
+///
+/// let cache = DownloadCache::new("cache/modfiles")
+///     .max_size(10 * 1024 * 1024 * 1024)
+///     .hardlink(true);
+///
+/// modio
+///     .download((5, 19))
+///     .cache(cache.clone())
+///     .save_to_dir("profiles/default/mods")
+///     .await?;
+///
+/// // The second download is a local hardlink of the cached file.
+/// modio
+///     .download((5, 19))
+///     .cache(cache.clone())
+///     .save_to_dir("profiles/modded/mods")
+///     .await?;
+///
+/// for entry in cache.entries().await? {
+///     println!("{} {} bytes", entry.md5, entry.size);
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+///
+/// [`Downloader::cache`]: super::Downloader::cache
+#[derive(Clone, Debug)]
+pub struct DownloadCache {
+    dir: PathBuf,
+    max_size: Option<u64>,
+    hardlink: bool,
+}
+
+/// A file of the [`DownloadCache`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct CacheEntry {
+    /// The MD5 hash of the modfile.
+    pub md5: String,
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// The time the file was last added or restored.
+    pub last_used: SystemTime,
+    /// The path of the cached file.
+    pub path: PathBuf,
+}
+
+impl DownloadCache {
+    /// Constructs a new `DownloadCache` that stores the modfiles in `dir`.
+    ///
+    /// The directory is created if it doesn't exist.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> DownloadCache {
+        DownloadCache {
+            dir: dir.into(),
+            max_size: None,
+            hardlink: false,
+        }
+    }
+
+    /// Limit the total size of the cached files in bytes.
+    ///
+    /// Defaults to no limit.
+    #[must_use]
+    pub fn max_size(self, max_size: u64) -> DownloadCache {
+        DownloadCache {
+            max_size: Some(max_size),
+            ..self
+        }
+    }
+
+    /// Hardlink cached files instead of copying them.
+    ///
+    /// Falls back to a copy if the file can't be linked, e.g. if the target is on another
+    /// filesystem. Linked files share their content with the cache and must not be modified.
+    /// Defaults to `false`.
+    #[must_use]
+    pub fn hardlink(self, hardlink: bool) -> DownloadCache {
+        DownloadCache { hardlink, ..self }
+    }
+
+    /// Returns the cache directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the cached file with the MD5 hash `md5`.
+    pub async fn get(&self, md5: &str) -> Result<Option<CacheEntry>> {
+        let path = match self.path(md5) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        match fs::metadata(&path).await {
+            Ok(m) => {
+                let md5 = md5.to_ascii_lowercase();
+                Ok(Some(CacheEntry {
+                    size: m.len(),
+                    last_used: self.last_used(&md5, &m).await?,
+                    md5,
+                    path,
+                }))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(error::decode(e)),
+        }
+    }
+
+    /// Returns the cached files, the most recently used first.
+    pub async fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut dir = match fs::read_dir(&self.dir).await {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(error::decode(e)),
+        };
+        let mut entries = Vec::new();
+        while let Some(entry) = dir.next_entry().await.map_err(error::decode)? {
+            let name = entry.file_name();
+            let md5 = match name.to_str().filter(|n| is_md5(n)) {
+                Some(md5) => md5.to_string(),
+                None => continue,
+            };
+            let m = entry.metadata().await.map_err(error::decode)?;
+            if !m.is_file() {
+                continue;
+            }
+            entries.push(CacheEntry {
+                size: m.len(),
+                last_used: self.last_used(&md5, &m).await?,
+                md5,
+                path: entry.path(),
+            });
+        }
+        entries.sort_by_key(|e| Reverse(e.last_used));
+        Ok(entries)
+    }
+
+    /// Returns the total size of the cached files in bytes.
+    pub async fn size(&self) -> Result<u64> {
+        let entries = self.entries().await?;
+        Ok(entries.iter().map(|e| e.size).sum())
+    }
+
+    /// Remove the cached file with the MD5 hash `md5`. Returns false if the file wasn't cached.
+    pub async fn remove(&self, md5: &str) -> Result<bool> {
+        match self.path(md5) {
+            Some(path) => {
+                remove_file(&self.used_path(md5)).await?;
+                remove_file(&path).await
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Remove the least recently used files until the cache fits into
+    /// [`max_size`](DownloadCache::max_size) and clean up leftovers of interrupted inserts and
+    /// removed files.
+    ///
+    /// Returns the number of freed bytes.
+    pub async fn gc(&self) -> Result<u64> {
+        self.remove_partial().await?;
+        self.evict().await
+    }
+
+    async fn evict(&self) -> Result<u64> {
+        let max_size = match self.max_size {
+            Some(max_size) => max_size,
+            None => return Ok(0),
+        };
+        let entries = self.entries().await?;
+        let mut size = entries.iter().map(|e| e.size).sum::<u64>();
+        let mut freed = 0;
+        for entry in entries.iter().rev() {
+            if size <= max_size {
+                break;
+            }
+            debug!("evicting cached file: {}", entry.md5);
+            remove_file(&self.used_path(&entry.md5)).await?;
+            if remove_file(&entry.path).await? {
+                freed += entry.size;
+            }
+            size -= entry.size;
+        }
+        Ok(freed)
+    }
+
+    /// Remove all cached files.
+    pub async fn clear(&self) -> Result<()> {
+        for entry in self.entries().await? {
+            remove_file(&self.used_path(&entry.md5)).await?;
+            remove_file(&entry.path).await?;
+        }
+        self.remove_partial().await
+    }
+
+    /// Copy or link the cached file of the modfile to `path`. Returns false if the modfile is
+    /// not cached.
+    pub(crate) async fn restore(&self, file: &File, path: &Path) -> Result<bool> {
+        let entry = match self.get(&file.filehash.md5).await? {
+            Some(entry) if entry.size == file.filesize => entry,
+            _ => return Ok(false),
+        };
+        debug!("restoring cached file {} to {}", entry.md5, path.display());
+        remove_file(path).await?;
+        self.link_or_copy(&entry.path, path).await?;
+        self.touch(&entry.md5).await?;
+        Ok(true)
+    }
+
+    /// Add the downloaded modfile at `path` to the cache once its size and hash match the
+    /// metadata of the modfile.
+    pub(crate) async fn insert(&self, file: &File, path: &Path) -> Result<()> {
+        let target = match self.path(&file.filehash.md5) {
+            Some(target) => target,
+            None => return Ok(()),
+        };
+        if matches!(self.max_size, Some(max) if file.filesize > max) {
+            debug!("file {} exceeds the cache size", file.id);
+            return Ok(());
+        }
+        let mut verifier = Verifier::new(file);
+        verifier.seed(path, file.filesize + 1).await?;
+        verifier.finish()?;
+
+        fs::create_dir_all(&self.dir).await.map_err(error::decode)?;
+        // Concurrent inserts of the same modfile use their own temporary file.
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let tmp = self.dir.join(format!(
+            ".{}.{}-{}.part",
+            file.filehash.md5,
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        remove_file(&tmp).await?;
+        if let Err(e) = self.link_or_copy(path, &tmp).await {
+            let _ = remove_file(&tmp).await;
+            return Err(e);
+        }
+        if let Err(e) = fs::rename(&tmp, &target).await {
+            let _ = remove_file(&tmp).await;
+            return Err(error::decode(e));
+        }
+        self.touch(&file.filehash.md5).await?;
+        debug!("cached file {} as {}", file.id, file.filehash.md5);
+
+        self.evict().await?;
+        Ok(())
+    }
+
+    fn path(&self, md5: &str) -> Option<PathBuf> {
+        if is_md5(md5) {
+            Some(self.dir.join(md5.to_ascii_lowercase()))
+        } else {
+            None
+        }
+    }
+
+    fn used_path(&self, md5: &str) -> PathBuf {
+        self.dir.join(format!(".{}.used", md5.to_ascii_lowercase()))
+    }
+
+    /// Mark the cached file as used by writing the current time to its `.used` file.
+    ///
+    /// The modification time of the cached file itself isn't updated because the file may be
+    /// shared with hardlinked copies.
+    async fn touch(&self, md5: &str) -> Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        fs::write(self.used_path(md5), now.to_string())
+            .await
+            .map_err(error::decode)
+    }
+
+    /// Returns the time of the last use of the cached file. Falls back to the modification
+    /// time of the file if the use wasn't recorded.
+    async fn last_used(&self, md5: &str, m: &Metadata) -> Result<SystemTime> {
+        if let Ok(used) = fs::read_to_string(self.used_path(md5)).await {
+            if let Ok(millis) = used.trim().parse() {
+                return Ok(UNIX_EPOCH + Duration::from_millis(millis));
+            }
+        }
+        m.modified().map_err(error::decode)
+    }
+
+    async fn link_or_copy(&self, src: &Path, dst: &Path) -> Result<()> {
+        if self.hardlink {
+            match fs::hard_link(src, dst).await {
+                Ok(()) => return Ok(()),
+                Err(e) => debug!("failed to link {}: {}, copying", src.display(), e),
+            }
+        }
+        fs::copy(src, dst).await.map_err(error::decode)?;
+        Ok(())
+    }
+
+    async fn remove_partial(&self) -> Result<()> {
+        let mut dir = match fs::read_dir(&self.dir).await {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(error::decode(e)),
+        };
+        while let Some(entry) = dir.next_entry().await.map_err(error::decode)? {
+            let name = entry.file_name();
+            let name = match name.to_str().and_then(|n| n.strip_prefix('.')) {
+                Some(name) => name,
+                None => continue,
+            };
+            let leftover = if let Some(tmp) = name.strip_suffix(".part") {
+                matches!(tmp.split('.').next(), Some(md5) if is_md5(md5))
+            } else if let Some(md5) = name.strip_suffix(".used") {
+                is_md5(md5) && fs::metadata(self.dir.join(md5)).await.is_err()
+            } else {
+                false
+            };
+            if leftover {
+                remove_file(&entry.path()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_md5(s: &str) -> bool {
+    s.len() == 32 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+async fn remove_file(path: &Path) -> Result<bool> {
+    match fs::remove_file(path).await {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(error::decode(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_md5;
+
+    #[test]
+    fn md5_names() {
+        assert!(is_md5("d41d8cd98f00b204e9800998ecf8427e"));
+        assert!(is_md5("D41D8CD98F00B204E9800998ECF8427E"));
+        assert!(!is_md5("d41d8cd98f00b204e9800998ecf8427"));
+        assert!(!is_md5("../../../../../../../etc/passwd"));
+        assert!(!is_md5(""));
+    }
+}
@@ -0,0 +1,235 @@
+//! Parallel download of byte ranges into a preallocated file.
+use std::io::SeekFrom;
+use std::iter;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use futures_util::{stream, StreamExt, TryStreamExt};
+use http::header::CONTENT_RANGE;
+use reqwest::{Response, StatusCode};
+use tokio::fs::{File as AsyncFile, OpenOptions};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt, BufWriter};
+use tracing::debug;
+use url::Url;
+
+use super::progress::{DownloadProgress, Progress, ProgressFn};
+use super::throttle::{Throttle, Throttled};
+use super::{fetch, parse_content_range_start, Downloader};
+use crate::cancel::CancelHandle;
+use crate::error::{self, Result};
+use crate::redact;
+use crate::Modio;
+
+/// Files are not split into ranges smaller than 1 MiB.
+pub(crate) const MIN_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// Maximum number of attempts to download a single range.
+const CHUNK_ATTEMPTS: u32 = 3;
+
+struct Context<'a> {
+    modio: &'a Modio,
+    url: &'a Url,
+    path: &'a Path,
+    progress: Option<&'a ProgressFn>,
+    cancel: Option<&'a CancelHandle>,
+    throttles: Vec<Throttle>,
+    total: u64,
+    downloaded: AtomicU64,
+    started: Instant,
+}
+
+impl Context<'_> {
+    fn report(&self, len: u64) {
+        let downloaded = self.downloaded.fetch_add(len, Ordering::SeqCst) + len;
+        if let Some(progress) = self.progress {
+            let elapsed = self.started.elapsed().as_secs_f64();
+            progress.call(&DownloadProgress {
+                downloaded,
+                total: Some(self.total),
+                speed: if elapsed > 0.0 {
+                    downloaded as f64 / elapsed
+                } else {
+                    0.0
+                },
+            });
+        }
+    }
+}
+
+/// Split the file into byte ranges of at least [`MIN_CHUNK_SIZE`].
+// `u64::div_ceil` requires Rust 1.73.
+#[allow(clippy::manual_div_ceil)]
+fn ranges(size: u64, chunks: usize) -> Vec<(u64, u64)> {
+    let chunks = (chunks as u64).min(size / MIN_CHUNK_SIZE).max(1);
+    let chunk_size = (size + chunks - 1) / chunks;
+    (0..chunks)
+        .map(|i| (i * chunk_size, ((i + 1) * chunk_size).min(size) - 1))
+        .collect()
+}
+
+/// Returns the number of byte ranges the file is split into.
+pub(crate) fn count(size: u64, chunks: usize) -> usize {
+    if size == 0 {
+        return 1;
+    }
+    ranges(size, chunks).len()
+}
+
+/// Download the file with `chunks` concurrent range requests into the file at `path`.
+///
+/// Returns the response of the first request if the server doesn't respond with partial
+/// content, e.g. because it doesn't support range requests or the download url has expired.
+/// The status of the returned response is not checked.
+pub(crate) async fn download(
+    downloader: &Downloader,
+    url: &Url,
+    size: u64,
+    path: &Path,
+) -> Result<Option<Response>> {
+    let modio = &downloader.modio;
+    let chunks = downloader.chunks;
+    let mut ranges = ranges(size, chunks).into_iter();
+    let (start, end) = ranges.next().expect("at least one range");
+
+    let resp = fetch(modio, url.clone(), start, Some(end)).await?;
+    if resp.status() != StatusCode::PARTIAL_CONTENT {
+        return Ok(Some(resp));
+    }
+
+    let out = AsyncFile::create(path).await.map_err(error::decode)?;
+    out.set_len(size).await.map_err(error::decode)?;
+    drop(out);
+
+    let ctx = Context {
+        modio,
+        url,
+        path,
+        progress: downloader.progress.as_ref(),
+        cancel: downloader.cancel.as_ref(),
+        throttles: downloader.throttles(),
+        total: size,
+        downloaded: AtomicU64::new(0),
+        started: Instant::now(),
+    };
+
+    debug!("downloading file in {} ranges", ranges.len() + 1);
+    // Collect the futures first, a lazy iterator would make the returned future `!Send`.
+    let first = download_range(&ctx, start, end, Some(resp));
+    let rest = ranges.map(|(start, end)| download_range(&ctx, start, end, None));
+    let ranges = iter::once(first).chain(rest).collect::<Vec<_>>();
+
+    stream::iter(ranges)
+        .buffer_unordered(chunks)
+        .try_collect::<Vec<()>>()
+        .await?;
+    Ok(None)
+}
+
+/// Download the range `start..=end` and retry with the remaining bytes if it fails.
+async fn download_range(
+    ctx: &Context<'_>,
+    start: u64,
+    end: u64,
+    mut resp: Option<Response>,
+) -> Result<()> {
+    let out = OpenOptions::new()
+        .write(true)
+        .open(ctx.path)
+        .await
+        .map_err(error::decode)?;
+    let mut out = BufWriter::new(out);
+    out.seek(SeekFrom::Start(start))
+        .await
+        .map_err(error::decode)?;
+
+    let mut pos = start;
+    let mut attempt = 1;
+    loop {
+        let result = write_range(ctx, &mut out, &mut pos, end, resp.take()).await;
+        out.flush().await.map_err(error::decode)?;
+        match result {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < CHUNK_ATTEMPTS && !e.is_cancelled() => {
+                debug!(
+                    "range {}-{} failed at byte {}: {}, retrying",
+                    start, end, pos, e
+                );
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+async fn write_range(
+    ctx: &Context<'_>,
+    out: &mut BufWriter<AsyncFile>,
+    pos: &mut u64,
+    end: u64,
+    resp: Option<Response>,
+) -> Result<()> {
+    let resp = match resp {
+        Some(resp) => resp,
+        None => fetch(ctx.modio, ctx.url.clone(), *pos, Some(end)).await?,
+    };
+    let start = resp
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_content_range_start);
+    if resp.status() != StatusCode::PARTIAL_CONTENT || start != Some(*pos) {
+        let resp = resp
+            .error_for_status()
+            .map_err(|e| error::request(redact::error(e)))?;
+        return Err(error::request(format!(
+            "unexpected response for range {}-{}: {}",
+            pos,
+            end,
+            resp.status()
+        )));
+    }
+
+    let stream = resp
+        .bytes_stream()
+        .map_err(|e| error::request(redact::error(e)));
+    let stream = Throttled::new(stream, ctx.throttles.clone());
+    let stream = Progress::new(stream, None, ctx.cancel.cloned(), 0, None);
+    futures_util::pin_mut!(stream);
+
+    while let Some(bytes) = stream.try_next().await? {
+        let len = (bytes.len() as u64).min(end + 1 - *pos);
+        out.write_all(&bytes[..len as usize])
+            .await
+            .map_err(error::decode)?;
+        *pos += len;
+        ctx.report(len);
+    }
+    if *pos != end + 1 {
+        return Err(error::request(format!(
+            "range ended at byte {} instead of {}",
+            pos,
+            end + 1
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ranges, MIN_CHUNK_SIZE};
+
+    #[test]
+    fn split_ranges() {
+        assert_eq!(ranges(100, 4), vec![(0, 99)]);
+
+        let size = 4 * MIN_CHUNK_SIZE + 1;
+        let list = ranges(size, 8);
+        assert_eq!(list.len(), 4);
+        assert_eq!(list[0].0, 0);
+        assert_eq!(list[3].1, size - 1);
+        for pair in list.windows(2) {
+            assert_eq!(pair[0].1 + 1, pair[1].0);
+        }
+    }
+}
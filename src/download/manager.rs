@@ -0,0 +1,596 @@
+//! Queue of downloads with a concurrency limit, priorities and retries.
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::error::Error as StdError;
+use std::fmt;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures_core::Stream;
+use tokio::sync::{mpsc, watch};
+use tracing::debug;
+
+use super::{DownloadAction, DownloadProgress, Downloader};
+use crate::cancel::CancelHandle;
+use crate::error::{Error, Result};
+use crate::retry::RetryPolicy;
+use crate::types::files::File;
+use crate::Modio;
+
+/// The default number of concurrent downloads.
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// The minimum interval between two progress events of a job.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+type Configure = dyn Fn(Downloader) -> Downloader + Send + Sync;
+
+/// Identifies a job of a [`DownloadManager`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JobId(u64);
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A download added to the [`DownloadManager`].
+#[derive(Debug)]
+pub struct DownloadJob {
+    action: DownloadAction,
+    target: Target,
+    priority: i32,
+}
+
+#[derive(Clone, Debug)]
+enum Target {
+    File(PathBuf),
+    Dir(PathBuf),
+}
+
+impl DownloadJob {
+    /// Download the modfile to the local file `path`. See [`Downloader::save_to_file`].
+    pub fn to_file<A, P>(action: A, path: P) -> DownloadJob
+    where
+        A: Into<DownloadAction>,
+        P: Into<PathBuf>,
+    {
+        DownloadJob {
+            action: action.into(),
+            target: Target::File(path.into()),
+            priority: 0,
+        }
+    }
+
+    /// Download the modfile into the directory `dir`. See [`Downloader::save_to_dir`].
+    pub fn to_dir<A, P>(action: A, dir: P) -> DownloadJob
+    where
+        A: Into<DownloadAction>,
+        P: Into<PathBuf>,
+    {
+        DownloadJob {
+            action: action.into(),
+            target: Target::Dir(dir.into()),
+            priority: 0,
+        }
+    }
+
+    /// Jobs with a higher priority are started first. Jobs with the same priority are started
+    /// in the order they were added.
+    ///
+    /// Defaults to `0`.
+    #[must_use]
+    pub fn priority(self, priority: i32) -> DownloadJob {
+        DownloadJob { priority, ..self }
+    }
+}
+
+/// The state of a job of the [`DownloadManager`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JobStatus {
+    /// The job is waiting for a free slot.
+    Queued,
+    /// The modfile is being downloaded.
+    Running,
+    /// The job has been paused with [`DownloadManager::pause`].
+    Paused,
+    /// The last attempt failed and the job waits for its next attempt.
+    Retrying,
+}
+
+/// The events of the jobs of a [`DownloadManager`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum DownloadEvent {
+    /// The job has been added or resumed and waits for a free slot.
+    Queued { id: JobId },
+    /// An attempt of the job has started. `attempt` starts at `1`.
+    Started { id: JobId, attempt: u32 },
+    /// The progress of the running job, reported at most every 100 ms.
+    Progress {
+        id: JobId,
+        progress: DownloadProgress,
+    },
+    /// The job has been paused.
+    Paused { id: JobId },
+    /// The attempt failed and the job is started again after `delay`.
+    Retrying {
+        id: JobId,
+        attempt: u32,
+        delay: Duration,
+        error: Arc<Error>,
+    },
+    /// The modfile has been saved to `path`.
+    Finished {
+        id: JobId,
+        path: PathBuf,
+        file: Box<File>,
+    },
+    /// The job failed and won't be retried.
+    Failed { id: JobId, error: Arc<Error> },
+}
+
+/// A queue of downloads that runs a limited number of jobs at a time.
+///
+/// Jobs are started in the order of their priority. Failed attempts are retried with the
+/// [`RetryPolicy`] of the manager and continue with the bytes already written. Client errors,
+/// e.g. a `404 Not Found` response, aren't retried. Running or queued jobs can be paused and
+/// resumed. Finished and failed jobs are removed from the manager and reported with the
+/// events of all jobs, available with [`DownloadManager::events`].
+///
+/// Jobs are spawned onto the Tokio runtime, so jobs must be added from within a runtime.
+///
+/// # Example
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use modio::download::{DownloadEvent, DownloadJob, DownloadManager};
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// #     let modio = modio::Modio::new("api-key")?;
+///
+/// let manager = DownloadManager::new(modio).concurrency(2);
+/// let mut events = manager.events();
+///
+/// for mod_id in [19, 20, 21] {
+///     manager.add(DownloadJob::to_dir((5, mod_id), "mods"));
+/// }
+/// let urgent = manager.add(DownloadJob::to_dir((5, 22), "mods").priority(10));
+///
+/// // The events end once the manager is dropped and the jobs are done.
+/// drop(manager);
+/// while let Some(event) = events.next().await {
+///     match event {
+///         DownloadEvent::Finished { id, path, .. } => println!("{}: {}", id, path.display()),
+///         DownloadEvent::Failed { id, error } => println!("{}: {}", id, error),
+///         _ => {}
+///     }
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct DownloadManager {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    modio: Modio,
+    state: Mutex<State>,
+    active: watch::Sender<usize>,
+    idle: watch::Receiver<usize>,
+}
+
+struct State {
+    concurrency: usize,
+    retry: RetryPolicy,
+    configure: Option<Arc<Configure>>,
+    next_id: u64,
+    next_seq: u64,
+    jobs: HashMap<JobId, Job>,
+    queue: BinaryHeap<(i32, Reverse<u64>, JobId)>,
+    running: usize,
+    subscribers: Vec<mpsc::UnboundedSender<DownloadEvent>>,
+}
+
+struct Job {
+    action: DownloadAction,
+    target: Target,
+    priority: i32,
+    status: JobStatus,
+    attempt: u32,
+    failures: u32,
+    cancel: Option<CancelHandle>,
+    /// The task of the last attempt is still running.
+    active: bool,
+}
+
+impl DownloadManager {
+    /// Constructs a new `DownloadManager` that downloads the modfiles with `modio`.
+    ///
+    /// By default 4 jobs run at a time and failed attempts are retried 3 times.
+    pub fn new(modio: Modio) -> DownloadManager {
+        let (active, idle) = watch::channel(0);
+        DownloadManager {
+            shared: Arc::new(Shared {
+                modio,
+                state: Mutex::new(State {
+                    concurrency: DEFAULT_CONCURRENCY,
+                    retry: RetryPolicy::new(3),
+                    configure: None,
+                    next_id: 0,
+                    next_seq: 0,
+                    jobs: HashMap::new(),
+                    queue: BinaryHeap::new(),
+                    running: 0,
+                    subscribers: Vec::new(),
+                }),
+                active,
+                idle,
+            }),
+        }
+    }
+
+    /// Set the maximum number of jobs that run at a time.
+    #[must_use]
+    pub fn concurrency(self, concurrency: usize) -> DownloadManager {
+        self.shared.lock().concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set the policy for retrying failed attempts.
+    #[must_use]
+    pub fn retry(self, retry: RetryPolicy) -> DownloadManager {
+        self.shared.lock().retry = retry;
+        self
+    }
+
+    /// Configure the [`Downloader`] of every attempt, e.g. to enable a
+    /// [`DownloadCache`](super::DownloadCache) or parallel downloads.
+    ///
+    /// Progress reporting, cancellation and resuming are set by the manager.
+    #[must_use]
+    pub fn configure<F>(self, f: F) -> DownloadManager
+    where
+        F: Fn(Downloader) -> Downloader + Send + Sync + 'static,
+    {
+        self.shared.lock().configure = Some(Arc::new(f));
+        self
+    }
+
+    /// Add a job to the queue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a Tokio runtime.
+    pub fn add(&self, job: DownloadJob) -> JobId {
+        let mut state = self.shared.lock();
+        let id = JobId(state.next_id);
+        state.next_id += 1;
+        state.jobs.insert(
+            id,
+            Job {
+                action: job.action,
+                target: job.target,
+                priority: job.priority,
+                status: JobStatus::Queued,
+                attempt: 0,
+                failures: 0,
+                cancel: None,
+                active: false,
+            },
+        );
+        self.shared.enqueue(&mut state, id);
+        self.shared.schedule(&mut state);
+        id
+    }
+
+    /// Pause a queued, running or retrying job. A running download is cancelled and continued
+    /// from the written bytes once the job is resumed.
+    ///
+    /// Returns false if the job can't be paused.
+    pub fn pause(&self, id: JobId) -> bool {
+        let mut state = self.shared.lock();
+        let job = match state.jobs.get_mut(&id) {
+            Some(job) => job,
+            None => return false,
+        };
+        match job.status {
+            JobStatus::Queued | JobStatus::Retrying => job.status = JobStatus::Paused,
+            JobStatus::Running => {
+                job.status = JobStatus::Paused;
+                if let Some(cancel) = job.cancel.take() {
+                    cancel.cancel();
+                }
+            }
+            _ => return false,
+        }
+        state.emit(DownloadEvent::Paused { id });
+        self.shared.update_active(&state);
+        true
+    }
+
+    /// Resume a paused job.
+    ///
+    /// Returns false if the job isn't paused.
+    pub fn resume(&self, id: JobId) -> bool {
+        let mut state = self.shared.lock();
+        match state.jobs.get_mut(&id) {
+            Some(job) if job.status == JobStatus::Paused => job.status = JobStatus::Queued,
+            _ => return false,
+        }
+        self.shared.enqueue(&mut state, id);
+        self.shared.schedule(&mut state);
+        true
+    }
+
+    /// Returns the status of the job, or `None` if the job has finished or failed.
+    pub fn status(&self, id: JobId) -> Option<JobStatus> {
+        self.shared.lock().jobs.get(&id).map(|job| job.status)
+    }
+
+    /// Returns a `Stream` of the events of all jobs added after this call.
+    ///
+    /// Events are buffered until they are received, so the stream should be polled
+    /// continuously. The stream ends once all handles of the manager are dropped and no job
+    /// is running or waiting for a retry.
+    pub fn events(&self) -> Events {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.shared.lock().subscribers.push(tx);
+        Events { rx }
+    }
+
+    /// Wait until all jobs that aren't paused have finished or failed.
+    pub async fn wait(&self) {
+        let mut idle = self.shared.idle.clone();
+        while *idle.borrow() > 0 {
+            if idle.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl fmt::Debug for DownloadManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.shared.lock();
+        f.debug_struct("DownloadManager")
+            .field("concurrency", &state.concurrency)
+            .field("retry", &state.retry)
+            .field("jobs", &state.jobs.len())
+            .field("running", &state.running)
+            .finish()
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn enqueue(&self, state: &mut State, id: JobId) {
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        let priority = state.jobs[&id].priority;
+        state.queue.push((priority, Reverse(seq), id));
+        state.emit(DownloadEvent::Queued { id });
+    }
+
+    /// Start queued jobs until the concurrency limit is reached.
+    fn schedule(self: &Arc<Self>, state: &mut State) {
+        while state.running < state.concurrency {
+            let id = match state.queue.pop() {
+                Some((_, _, id)) => id,
+                None => break,
+            };
+            let job = match state.jobs.get_mut(&id) {
+                Some(job) if job.status == JobStatus::Queued && !job.active => job,
+                // Skip paused jobs, duplicate entries of resumed jobs and jobs whose paused
+                // attempt hasn't stopped yet. The latter are queued again once it stopped.
+                _ => continue,
+            };
+            let cancel = CancelHandle::new();
+            job.status = JobStatus::Running;
+            job.attempt += 1;
+            job.cancel = Some(cancel.clone());
+            job.active = true;
+            let attempt = job.attempt;
+            let action = job.action.clone();
+            let target = job.target.clone();
+
+            state.running += 1;
+            state.emit(DownloadEvent::Started { id, attempt });
+
+            let downloader = self.downloader(state, id, action, cancel, attempt > 1);
+            let shared = Arc::clone(self);
+            tokio::spawn(async move {
+                let result = run(&shared, id, downloader, target).await;
+                shared.complete(id, result);
+            });
+        }
+        self.update_active(state);
+    }
+
+    fn downloader(
+        self: &Arc<Self>,
+        state: &State,
+        id: JobId,
+        action: DownloadAction,
+        cancel: CancelHandle,
+        resume: bool,
+    ) -> Downloader {
+        let mut downloader = self.modio.download(action);
+        if let Some(configure) = &state.configure {
+            downloader = configure(downloader);
+        }
+        let shared = Arc::downgrade(self);
+        let last = Mutex::new(None::<Instant>);
+        downloader
+            .resume(resume)
+            .cancel_handle(cancel)
+            .progress(move |progress| {
+                // Don't contend for the state of the manager on every received chunk.
+                let done = progress.total == Some(progress.downloaded);
+                {
+                    let mut last = last.lock().unwrap();
+                    if !done && matches!(*last, Some(t) if t.elapsed() < PROGRESS_INTERVAL) {
+                        return;
+                    }
+                    *last = Some(Instant::now());
+                }
+                if let Some(shared) = shared.upgrade() {
+                    shared.lock().emit(DownloadEvent::Progress {
+                        id,
+                        progress: progress.clone(),
+                    });
+                }
+            })
+    }
+
+    fn complete(self: &Arc<Self>, id: JobId, result: Result<(PathBuf, File)>) {
+        let mut state = self.lock();
+        state.running -= 1;
+        let retry = state.retry.clone();
+        let job = state.jobs.get_mut(&id).expect("job of a running task");
+        job.cancel = None;
+        job.active = false;
+
+        let event = match result {
+            Ok((path, file)) => {
+                state.jobs.remove(&id);
+                Some(DownloadEvent::Finished {
+                    id,
+                    path,
+                    file: Box::new(file),
+                })
+            }
+            // The attempt has been cancelled by `pause`.
+            Err(_) if job.status == JobStatus::Paused => None,
+            // The job has been resumed before the cancelled attempt stopped.
+            Err(_) if job.status == JobStatus::Queued => {
+                let (priority, seq) = (job.priority, state.next_seq);
+                state.next_seq += 1;
+                state.queue.push((priority, Reverse(seq), id));
+                None
+            }
+            Err(e) => {
+                job.failures += 1;
+                let delay = if is_client_error(&e) {
+                    None
+                } else {
+                    retry.delay_for(job.failures, &e)
+                };
+                match delay {
+                    Some(delay) => {
+                        debug!("download job {} failed: {}, retrying in {:?}", id, e, delay);
+                        job.status = JobStatus::Retrying;
+                        let shared = Arc::clone(self);
+                        tokio::spawn(async move {
+                            tokio::time::sleep(delay).await;
+                            shared.requeue(id);
+                        });
+                        Some(DownloadEvent::Retrying {
+                            id,
+                            attempt: job.attempt,
+                            delay,
+                            error: Arc::new(e),
+                        })
+                    }
+                    None => {
+                        state.jobs.remove(&id);
+                        Some(DownloadEvent::Failed {
+                            id,
+                            error: Arc::new(e),
+                        })
+                    }
+                }
+            }
+        };
+        if let Some(event) = event {
+            state.emit(event);
+        }
+        self.schedule(&mut state);
+    }
+
+    fn requeue(self: &Arc<Self>, id: JobId) {
+        let mut state = self.lock();
+        match state.jobs.get_mut(&id) {
+            Some(job) if job.status == JobStatus::Retrying => job.status = JobStatus::Queued,
+            _ => return,
+        }
+        self.enqueue(&mut state, id);
+        self.schedule(&mut state);
+    }
+
+    /// Publish the number of jobs that aren't paused for `wait`.
+    fn update_active(&self, state: &State) {
+        let active = state
+            .jobs
+            .values()
+            .filter(|job| {
+                matches!(
+                    job.status,
+                    JobStatus::Queued | JobStatus::Running | JobStatus::Retrying
+                )
+            })
+            .count();
+        if *self.idle.borrow() != active {
+            let _ = self.active.send(active);
+        }
+    }
+}
+
+impl State {
+    fn emit(&mut self, event: DownloadEvent) {
+        self.subscribers.retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+/// Returns `true` if the error is a client error response, which fails again if retried.
+fn is_client_error(err: &Error) -> bool {
+    let status = err
+        .status()
+        .or_else(|| err.source()?.downcast_ref::<reqwest::Error>()?.status());
+    matches!(status, Some(status) if status.is_client_error())
+}
+
+/// Resolve the modfile and save it to the target of the job.
+async fn run(
+    shared: &Shared,
+    id: JobId,
+    downloader: Downloader,
+    target: Target,
+) -> Result<(PathBuf, File)> {
+    let download = downloader.resolve().await?;
+    let file = download.file().clone();
+
+    // Retries and resumed jobs download the same modfile without resolving it again.
+    if let Some(job) = shared.lock().jobs.get_mut(&id) {
+        job.action = DownloadAction::from(file.clone());
+    }
+
+    match target {
+        Target::File(path) => {
+            download.save_to_file(&path).await?;
+            Ok((path, file))
+        }
+        Target::Dir(dir) => download.save_to_dir(dir).await,
+    }
+}
+
+/// `Stream` of [`DownloadEvent`]s, constructed with [`DownloadManager::events`].
+#[derive(Debug)]
+pub struct Events {
+    rx: mpsc::UnboundedReceiver<DownloadEvent>,
+}
+
+impl Stream for Events {
+    type Item = DownloadEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
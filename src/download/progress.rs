@@ -0,0 +1,128 @@
+//! Progress reporting and cancellation of downloads.
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use bytes::Bytes;
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+
+use crate::cancel::CancelHandle;
+use crate::error::{self, Result};
+
+/// Progress of a download passed to the callback of [`Downloader::progress`].
+///
+/// [`Downloader::progress`]: super::Downloader::progress
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct DownloadProgress {
+    /// The number of bytes downloaded, including the bytes of a resumed download.
+    pub downloaded: u64,
+    /// The total size of the file from the `Content-Length` header or the modfile.
+    pub total: Option<u64>,
+    /// The average download speed of the transfer in bytes per second.
+    pub speed: f64,
+}
+
+type Callback = dyn Fn(&DownloadProgress) + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) struct ProgressFn(Arc<Callback>);
+
+impl ProgressFn {
+    pub(crate) fn new<F>(f: F) -> ProgressFn
+    where
+        F: Fn(&DownloadProgress) + Send + Sync + 'static,
+    {
+        ProgressFn(Arc::new(f))
+    }
+
+    pub(crate) fn call(&self, progress: &DownloadProgress) {
+        (self.0)(progress);
+    }
+}
+
+impl fmt::Debug for ProgressFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressFn")
+    }
+}
+
+pin_project! {
+    /// Stream of bytes that reports the progress and stops once the download is cancelled.
+    pub(crate) struct Progress<S> {
+        #[pin]
+        inner: S,
+        callback: Option<ProgressFn>,
+        cancel: Option<CancelHandle>,
+        offset: u64,
+        downloaded: u64,
+        total: Option<u64>,
+        started: Instant,
+        done: bool,
+    }
+}
+
+impl<S> Progress<S> {
+    pub(crate) fn new(
+        inner: S,
+        callback: Option<ProgressFn>,
+        cancel: Option<CancelHandle>,
+        offset: u64,
+        total: Option<u64>,
+    ) -> Progress<S> {
+        Progress {
+            inner,
+            callback,
+            cancel,
+            offset,
+            downloaded: 0,
+            total,
+            started: Instant::now(),
+            done: false,
+        }
+    }
+}
+
+impl<S> Stream for Progress<S>
+where
+    S: Stream<Item = Result<Bytes>>,
+{
+    type Item = Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+        if let Some(cancel) = this.cancel {
+            if cancel.poll_cancelled(cx) {
+                *this.done = true;
+                return Poll::Ready(Some(Err(error::cancelled())));
+            }
+        }
+        let item = futures_core::ready!(this.inner.poll_next(cx));
+        match item {
+            Some(Ok(ref bytes)) => {
+                *this.downloaded += bytes.len() as u64;
+                if let Some(callback) = this.callback {
+                    let elapsed = this.started.elapsed().as_secs_f64();
+                    let speed = if elapsed > 0.0 {
+                        *this.downloaded as f64 / elapsed
+                    } else {
+                        0.0
+                    };
+                    callback.call(&DownloadProgress {
+                        downloaded: *this.offset + *this.downloaded,
+                        total: *this.total,
+                        speed,
+                    });
+                }
+            }
+            Some(Err(_)) | None => *this.done = true,
+        }
+        Poll::Ready(item)
+    }
+}
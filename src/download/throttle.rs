@@ -0,0 +1,212 @@
+//! Bandwidth limit for downloads.
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+use tokio::time::{sleep, Instant, Sleep};
+
+use crate::error::Result;
+
+/// A bandwidth limit in bytes per second shared by all downloads using it.
+///
+/// The limit is a token bucket that holds up to one second of traffic. Downloads take a
+/// token for every received byte and pause once the bucket is empty. The limit can be changed
+/// or removed at runtime with [`Throttle::set_limit`], and clones of the throttle share the
+/// same limit.
+///
+/// # Example
+/// ```no_run
+/// use modio::download::Throttle;
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+///
+/// // Limit all downloads of the client to 1 MiB/s while the game is running.
+/// let throttle = Throttle::new(1024 * 1024);
+/// let modio = modio::Modio::builder("api-key")
+///     .throttle(throttle.clone())
+///     .build()?;
+///
+/// // Limit a single download even further.
+/// let download = modio
+///     .download((5, 19))
+///     .throttle(Throttle::new(256 * 1024))
+///     .save_to_file("mod.zip");
+///
+/// // Remove the client-wide limit once the game is idle.
+/// throttle.set_limit(None);
+/// download.await?;
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Throttle {
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    limit: Option<u64>,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Throttle {
+    /// Constructs a new `Throttle` with a limit of `bytes_per_sec`.
+    pub fn new(bytes_per_sec: u64) -> Throttle {
+        Throttle::with_limit(Some(bytes_per_sec))
+    }
+
+    /// Constructs a new `Throttle` without a limit.
+    pub fn unlimited() -> Throttle {
+        Throttle::with_limit(None)
+    }
+
+    fn with_limit(limit: Option<u64>) -> Throttle {
+        let limit = limit.map(|l| l.max(1));
+        Throttle {
+            state: Arc::new(Mutex::new(State {
+                limit,
+                tokens: limit.unwrap_or_default() as f64,
+                updated: Instant::now(),
+            })),
+        }
+    }
+
+    /// Returns the limit in bytes per second.
+    pub fn limit(&self) -> Option<u64> {
+        self.lock().limit
+    }
+
+    /// Set the limit in bytes per second or remove it with `None`.
+    ///
+    /// The new limit applies to the next chunk of every running download.
+    pub fn set_limit(&self, bytes_per_sec: Option<u64>) {
+        let mut state = self.lock();
+        let now = Instant::now();
+        state.refill(now);
+        let previous = state.limit;
+        state.limit = bytes_per_sec.map(|l| l.max(1));
+        match (previous, state.limit) {
+            (None, Some(limit)) => state.tokens = limit as f64,
+            // Forgive the debt accumulated with the previous limit.
+            (Some(_), Some(limit)) => state.tokens = state.tokens.clamp(0.0, limit as f64),
+            (_, None) => {}
+        }
+    }
+
+    /// Takes `len` tokens from the bucket and returns the time until the bucket is no longer
+    /// in debt.
+    fn reserve(&self, len: usize) -> Option<Duration> {
+        let mut state = self.lock();
+        let limit = state.limit?;
+        state.refill(Instant::now());
+        state.tokens -= len as f64;
+        if state.tokens < 0.0 {
+            Some(Duration::from_secs_f64(-state.tokens / limit as f64))
+        } else {
+            None
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("throttle state poisoned")
+    }
+}
+
+impl State {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.updated = now;
+        if let Some(limit) = self.limit {
+            let limit = limit as f64;
+            self.tokens = (self.tokens + elapsed * limit).min(limit);
+        }
+    }
+}
+
+impl fmt::Debug for Throttle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Throttle")
+            .field("limit", &self.limit())
+            .finish()
+    }
+}
+
+pin_project! {
+    /// Stream of bytes that holds back every chunk until the throttles allow its bytes.
+    pub(crate) struct Throttled<S: Stream> {
+        #[pin]
+        inner: S,
+        throttles: Vec<Throttle>,
+        delay: Option<Pin<Box<Sleep>>>,
+        pending: Option<S::Item>,
+    }
+}
+
+impl<S: Stream> Throttled<S> {
+    pub(crate) fn new(inner: S, throttles: Vec<Throttle>) -> Throttled<S> {
+        Throttled {
+            inner,
+            throttles,
+            delay: None,
+            pending: None,
+        }
+    }
+}
+
+impl<S> Stream for Throttled<S>
+where
+    S: Stream<Item = Result<Bytes>>,
+{
+    type Item = Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        if let Some(delay) = this.delay {
+            futures_core::ready!(delay.as_mut().poll(cx));
+            *this.delay = None;
+            return Poll::Ready(this.pending.take());
+        }
+        let item = futures_core::ready!(this.inner.poll_next(cx));
+        if let Some(Ok(ref bytes)) = item {
+            let wait = this
+                .throttles
+                .iter()
+                .filter_map(|t| t.reserve(bytes.len()))
+                .max();
+            if let Some(wait) = wait {
+                let mut delay = Box::pin(sleep(wait));
+                if delay.as_mut().poll(cx).is_pending() {
+                    *this.delay = Some(delay);
+                    *this.pending = item;
+                    return Poll::Pending;
+                }
+            }
+        }
+        Poll::Ready(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Throttle;
+
+    #[tokio::test(start_paused = true)]
+    async fn token_bucket() {
+        let throttle = Throttle::new(100);
+        assert_eq!(throttle.reserve(100), None);
+        assert_eq!(throttle.reserve(50), Some(Duration::from_millis(500)));
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(throttle.reserve(50), None);
+
+        throttle.set_limit(None);
+        assert_eq!(throttle.reserve(1_000_000), None);
+    }
+}
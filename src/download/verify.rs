@@ -0,0 +1,163 @@
+//! Size and MD5 verification of downloaded files.
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+use tokio::fs::File as AsyncFile;
+use tokio::io::AsyncReadExt;
+
+use crate::error::{self, Result};
+use crate::types::files::File;
+
+/// Computes the size and the MD5 hash of the downloaded bytes and compares them with the
+/// metadata of the modfile.
+pub(crate) struct Verifier {
+    file_id: u32,
+    expected_size: u64,
+    expected_md5: String,
+    size: u64,
+    md5: md5::Context,
+}
+
+impl Verifier {
+    pub(crate) fn new(file: &File) -> Verifier {
+        Verifier {
+            file_id: file.id,
+            expected_size: file.filesize,
+            expected_md5: file.filehash.md5.to_ascii_lowercase(),
+            size: 0,
+            md5: md5::Context::new(),
+        }
+    }
+
+    /// Feed the first `len` bytes of a partially downloaded file into the verifier.
+    pub(crate) async fn seed(&mut self, path: &Path, len: u64) -> Result<()> {
+        let file = AsyncFile::open(path).await.map_err(error::decode)?;
+        let mut file = file.take(len);
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf).await.map_err(error::decode)?;
+            if n == 0 {
+                break;
+            }
+            self.update(&buf[..n]);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        self.size += bytes.len() as u64;
+        self.md5.consume(bytes);
+    }
+
+    pub(crate) fn finish(self) -> Result<()> {
+        if self.size != self.expected_size {
+            return Err(error::download_size_mismatch(
+                self.file_id,
+                self.expected_size,
+                self.size,
+            ));
+        }
+        // Skip the hash check if the modfile has no hash.
+        if self.expected_md5.is_empty() {
+            return Ok(());
+        }
+        let actual = format!("{:x}", self.md5.compute());
+        if actual != self.expected_md5 {
+            return Err(error::download_hash_mismatch(
+                self.file_id,
+                self.expected_md5,
+                actual,
+            ));
+        }
+        Ok(())
+    }
+}
+
+pin_project! {
+    /// Stream of bytes that verifies the downloaded file at the end of the stream.
+    pub(crate) struct Verify<S> {
+        #[pin]
+        inner: S,
+        verifier: Option<Verifier>,
+    }
+}
+
+impl<S> Verify<S> {
+    pub(crate) fn new(inner: S, verifier: Option<Verifier>) -> Verify<S> {
+        Verify { inner, verifier }
+    }
+}
+
+impl<S> Stream for Verify<S>
+where
+    S: Stream<Item = Result<Bytes>>,
+{
+    type Item = Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        match futures_core::ready!(this.inner.poll_next(cx)) {
+            Some(Ok(bytes)) => {
+                if let Some(verifier) = this.verifier {
+                    verifier.update(&bytes);
+                }
+                Poll::Ready(Some(Ok(bytes)))
+            }
+            Some(Err(e)) => {
+                this.verifier.take();
+                Poll::Ready(Some(Err(e)))
+            }
+            None => match this.verifier.take().map(Verifier::finish) {
+                Some(Err(e)) => Poll::Ready(Some(Err(e))),
+                _ => Poll::Ready(None),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{stream, TryStreamExt};
+
+    use super::*;
+
+    fn file(size: u64, md5: &str) -> File {
+        serde_json::from_value(serde_json::json!({
+            "id": 1, "mod_id": 1, "date_added": 0, "date_scanned": 0, "virus_status": 0,
+            "virus_positive": 0, "virustotal_hash": null, "filesize": size,
+            "filehash": {"md5": md5}, "filename": "mod.zip", "version": null, "changelog": null,
+            "metadata_blob": null, "platforms": [],
+            "download": {"binary_url": "https://example.com/mod.zip", "date_expires": 0},
+        }))
+        .unwrap()
+    }
+
+    async fn verify(file: &File) -> Result<Vec<Bytes>> {
+        let chunks = vec![Ok(Bytes::from("hello ")), Ok(Bytes::from("world"))];
+        Verify::new(stream::iter(chunks), Some(Verifier::new(file)))
+            .try_collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn verified_stream() {
+        let f = file(11, "5EB63BBBE01EEED093CB22BB8F5ACDC3");
+        assert_eq!(verify(&f).await.unwrap().len(), 2);
+        assert!(verify(&file(11, "")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn mismatched_stream() {
+        let err = verify(&file(12, "")).await.unwrap_err();
+        assert!(err.is_download());
+        assert!(err.to_string().contains("size mismatch"));
+
+        let err = verify(&file(11, "00000000000000000000000000000000")).await;
+        let err = err.unwrap_err();
+        assert!(err.to_string().contains("hash mismatch"));
+    }
+}
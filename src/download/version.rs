@@ -0,0 +1,185 @@
+//! Selection of modfiles by a semver version requirement.
+use std::cmp::Ordering;
+
+use semver::{Comparator, Op, Version, VersionReq};
+
+use super::ResolvePolicy;
+use crate::error::{self, Result};
+use crate::types::files::File;
+
+/// Parse a version leniently as semver.
+///
+/// A leading `v` is ignored, missing minor and patch numbers are filled with `0` and leading
+/// zeros are removed, e.g. `v1.4` is parsed as `1.4.0` and `1.04-beta` as `1.4.0-beta`.
+pub(crate) fn parse_version(version: &str) -> Option<Version> {
+    let version = version.trim();
+    let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+    if let Ok(version) = Version::parse(version) {
+        return Some(version);
+    }
+
+    let end = version.find(['-', '+']).unwrap_or(version.len());
+    let (core, rest) = version.split_at(end);
+    let mut numbers = core.split('.').map(|n| n.parse::<u64>().ok());
+    let major = numbers.next()??;
+    let minor = numbers.next().unwrap_or(Some(0))?;
+    let patch = numbers.next().unwrap_or(Some(0))?;
+    if numbers.next().is_some() {
+        return None;
+    }
+    Version::parse(&format!("{}.{}.{}{}", major, minor, patch, rest)).ok()
+}
+
+/// Select the file with the highest version matching `req`.
+///
+/// `files` are expected to be ordered by their date added, newest first, so that the newest
+/// file is selected if multiple files have the same version.
+pub(crate) fn select(
+    game_id: u32,
+    mod_id: u32,
+    req: &VersionReq,
+    policy: &ResolvePolicy,
+    files: Vec<File>,
+) -> Result<File> {
+    let mut matches = Vec::new();
+    for file in files {
+        let version = match file.version.as_deref().map(|v| (v, parse_version(v))) {
+            Some((_, Some(version))) => version,
+            Some((raw, None)) if matches!(policy, ResolvePolicy::FailOnInvalid) => {
+                return Err(error::download_invalid_version(
+                    game_id,
+                    mod_id,
+                    file.id,
+                    raw.to_string(),
+                ));
+            }
+            _ => continue,
+        };
+        if matches_req(req, &version, policy) {
+            matches.push((version, file));
+        }
+    }
+
+    // Stable sort to keep the newest file first for equal versions.
+    matches.sort_by(|(a, _), (b, _)| b.cmp(a));
+    let mut matches = matches.into_iter();
+    let (version, file) = match matches.next() {
+        Some(found) => found,
+        None => {
+            return Err(error::download_version_not_found(
+                game_id,
+                mod_id,
+                req.to_string(),
+            ))
+        }
+    };
+    let ambiguous = matches!(matches.next(), Some((v, _)) if v == version);
+    if ambiguous && matches!(policy, ResolvePolicy::Fail) {
+        return Err(error::download_multiple_files(
+            game_id,
+            mod_id,
+            version.to_string(),
+        ));
+    }
+    Ok(file)
+}
+
+fn matches_req(req: &VersionReq, version: &Version, policy: &ResolvePolicy) -> bool {
+    // `VersionReq` only matches prereleases of versions that are named in the requirement.
+    if matches!(policy, ResolvePolicy::Prerelease) && !version.pre.is_empty() {
+        return req
+            .comparators
+            .iter()
+            .all(|cmp| matches_comparator(cmp, version));
+    }
+    req.matches(version)
+}
+
+/// Match a comparator with prereleases ordered below their release, e.g. `1.4.0-beta` doesn't
+/// match `^1.4` but `1.6.0-beta` matches `<1.6`.
+///
+/// Implicit upper bounds of partial versions, carets, tildes and wildcards exclude the
+/// prereleases of the next version, e.g. `2.0.0-beta` doesn't match `^1.4`.
+fn matches_comparator(cmp: &Comparator, version: &Version) -> bool {
+    // Compare the version with the comparator filled with zeros, ignoring build metadata.
+    let full = (version.major, version.minor, version.patch, &version.pre).cmp(&(
+        cmp.major,
+        cmp.minor.unwrap_or(0),
+        cmp.patch.unwrap_or(0),
+        &cmp.pre,
+    ));
+    // Compare only the components named by the comparator.
+    let prefix = version
+        .major
+        .cmp(&cmp.major)
+        .then_with(|| cmp.minor.map_or(Ordering::Equal, |m| version.minor.cmp(&m)))
+        .then_with(|| cmp.patch.map_or(Ordering::Equal, |p| version.patch.cmp(&p)));
+    let partial = cmp.patch.is_none();
+
+    match cmp.op {
+        Op::Exact | Op::Wildcard if partial => full != Ordering::Less && prefix == Ordering::Equal,
+        Op::Exact | Op::Wildcard => full == Ordering::Equal,
+        Op::Greater if partial => prefix == Ordering::Greater,
+        Op::Greater => full == Ordering::Greater,
+        Op::GreaterEq => full != Ordering::Less,
+        Op::Less => full == Ordering::Less,
+        Op::LessEq if partial => prefix != Ordering::Greater,
+        Op::LessEq => full != Ordering::Greater,
+        Op::Tilde => {
+            let same = version.major == cmp.major
+                && (cmp.minor.is_none() || cmp.minor == Some(version.minor));
+            full != Ordering::Less && same
+        }
+        Op::Caret => {
+            let same = if cmp.major > 0 || cmp.minor.is_none() {
+                version.major == cmp.major
+            } else if cmp.minor != Some(0) || partial {
+                version.major == cmp.major && Some(version.minor) == cmp.minor
+            } else {
+                prefix == Ordering::Equal
+            };
+            full != Ordering::Less && same
+        }
+        _ => cmp.matches(version),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::{Version, VersionReq};
+
+    use super::{matches_req, parse_version};
+    use crate::download::ResolvePolicy;
+
+    #[test]
+    fn lenient_versions() {
+        let v = |s| Some(Version::parse(s).unwrap());
+        assert_eq!(parse_version("1.4.2"), v("1.4.2"));
+        assert_eq!(parse_version(" v1.4 "), v("1.4.0"));
+        assert_eq!(parse_version("2"), v("2.0.0"));
+        assert_eq!(parse_version("1.04-beta.1"), v("1.4.0-beta.1"));
+        assert_eq!(parse_version("1.4+build.5"), v("1.4.0+build.5"));
+        assert_eq!(parse_version("1.2.3.4"), None);
+        assert_eq!(parse_version("latest"), None);
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[test]
+    fn prerelease_ordering() {
+        let matches = |req: &str, version: &str| {
+            let req = VersionReq::parse(req).unwrap();
+            let version = Version::parse(version).unwrap();
+            matches_req(&req, &version, &ResolvePolicy::Prerelease)
+        };
+        assert!(matches("^1.4", "1.6.0-beta"));
+        assert!(matches("<1.6", "1.6.0-beta"));
+        assert!(matches(">=1.4.0-alpha", "1.4.0-beta"));
+        assert!(matches("~1.4", "1.4.2-rc.1"));
+        assert!(!matches("^1.4", "1.4.0-beta"));
+        assert!(!matches("=1.4.0", "1.4.0-beta"));
+        assert!(!matches("^1.6", "1.6.0-beta"));
+        assert!(!matches("^1.4", "2.0.0-beta"));
+        assert!(!matches("<=1.5", "1.6.0-beta"));
+        assert!(!matches("^0.2.3", "0.3.0-beta"));
+    }
+}
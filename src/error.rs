@@ -8,7 +8,10 @@ use reqwest::StatusCode;
 
 use crate::auth::Error as AuthError;
 use crate::download::Error as DownloadError;
+use crate::files::Error as UploadError;
+use crate::types::files::{VirusResult, VirusStatus};
 use crate::types::Error as ModioError;
+use crate::TargetPlatform;
 
 /// A `Result` alias where the `Err` case is `modio::Error`.
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -23,6 +26,7 @@ type BoxError = Box<dyn StdError + Send + Sync>;
 struct Inner {
     kind: Kind,
     error_ref: Option<u16>,
+    request_id: Option<String>,
     source: Option<BoxError>,
 }
 
@@ -35,6 +39,7 @@ impl Error {
             inner: Box::new(Inner {
                 kind,
                 error_ref: None,
+                request_id: None,
                 source: source.map(Into::into),
             }),
         }
@@ -48,11 +53,17 @@ impl Error {
             inner: Box::new(Inner {
                 kind,
                 error_ref: Some(error_ref),
+                request_id: None,
                 source: source.map(Into::into),
             }),
         }
     }
 
+    pub(crate) fn with_request_id(mut self, request_id: Option<String>) -> Error {
+        self.inner.request_id = request_id;
+        self
+    }
+
     /// Returns true if the API key/access token is incorrect, revoked, expired or the request
     /// needs a different authentication method.
     pub fn is_auth(&self) -> bool {
@@ -79,6 +90,31 @@ impl Error {
         matches!(self.inner.kind, Kind::Download)
     }
 
+    /// Returns true if the file was refused before it was uploaded with
+    /// [`Files::add`](crate::files::Files::add) or a packaged mod was modified during the upload.
+    pub fn is_upload(&self) -> bool {
+        matches!(self.inner.kind, Kind::Upload)
+    }
+
+    /// Returns true if the operation was cancelled with a [`CancelHandle`](crate::CancelHandle).
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.inner.kind, Kind::Cancelled)
+    }
+
+    /// Returns true if the error is from extracting a modfile with the
+    /// [`Extractor`](crate::extract::Extractor).
+    #[cfg(feature = "extract")]
+    pub fn is_extract(&self) -> bool {
+        matches!(self.inner.kind, Kind::Extract)
+    }
+
+    /// Returns true if the error is from publishing a mod with the
+    /// [`Publisher`](crate::publish::Publisher).
+    #[cfg(feature = "publish")]
+    pub fn is_publish(&self) -> bool {
+        matches!(self.inner.kind, Kind::Publish)
+    }
+
     /// Returns true if the rate limit associated with credentials has been exhausted.
     pub fn is_ratelimited(&self) -> bool {
         matches!(self.inner.kind, Kind::RateLimit { .. })
@@ -106,6 +142,13 @@ impl Error {
         self.inner.error_ref
     }
 
+    /// Returns the unique id of the request if the error was generated from a response.
+    ///
+    /// Include it when contacting the mod.io support about a failed request.
+    pub fn request_id(&self) -> Option<&str> {
+        self.inner.request_id.as_deref()
+    }
+
     /// Returns status code if the error was generated from a response.
     pub fn status(&self) -> Option<StatusCode> {
         match self.inner.kind {
@@ -133,6 +176,10 @@ impl fmt::Debug for Error {
 
         builder.field("kind", &self.inner.kind);
 
+        if let Some(ref request_id) = self.inner.request_id {
+            builder.field("request_id", request_id);
+        }
+
         if let Some(ref source) = self.inner.source {
             builder.field("source", source);
         }
@@ -145,8 +192,14 @@ impl fmt::Display for Error {
         match self.inner.kind {
             Kind::Auth(ref err) => write!(f, "authentication error: {}", err)?,
             Kind::Builder => f.write_str("builder error")?,
+            Kind::Cancelled => f.write_str("operation cancelled")?,
             Kind::Decode => f.write_str("error decoding response body")?,
             Kind::Download => f.write_str("download error")?,
+            Kind::Upload => f.write_str("upload error")?,
+            #[cfg(feature = "extract")]
+            Kind::Extract => f.write_str("extract error")?,
+            #[cfg(feature = "publish")]
+            Kind::Publish => f.write_str("publish error")?,
             Kind::Request => f.write_str("http request error")?,
             Kind::Status(code) => {
                 let prefix = if code.is_client_error() {
@@ -181,9 +234,17 @@ impl StdError for Error {
 pub(crate) enum Kind {
     Auth(AuthError),
     Download,
+    Upload,
+    #[cfg(feature = "extract")]
+    Extract,
+    #[cfg(feature = "publish")]
+    Publish,
     Validation(String, HashMap<String, String>),
-    RateLimit { reset: Duration },
+    RateLimit {
+        reset: Duration,
+    },
     Builder,
+    Cancelled,
     Request,
     Decode,
     Status(StatusCode),
@@ -225,14 +286,6 @@ pub(crate) fn terms_required() -> Error {
     )
 }
 
-pub(crate) fn builder_or_request(e: reqwest::Error) -> Error {
-    if e.is_builder() {
-        builder(e)
-    } else {
-        request(e)
-    }
-}
-
 pub(crate) fn builder<E: Into<BoxError>>(e: E) -> Error {
     Error::new(Kind::Builder, Some(e))
 }
@@ -241,6 +294,48 @@ pub(crate) fn request<E: Into<BoxError>>(e: E) -> Error {
     Error::new(Kind::Request, Some(e))
 }
 
+pub(crate) fn cancelled() -> Error {
+    Error::new(Kind::Cancelled, None::<Error>)
+}
+
+#[cfg(feature = "extract")]
+pub(crate) fn extract(e: crate::extract::Error) -> Error {
+    Error::new(Kind::Extract, Some(e))
+}
+
+#[cfg(feature = "publish")]
+pub(crate) fn publish(e: crate::publish::Error) -> Error {
+    Error::new(Kind::Publish, Some(e))
+}
+
+pub(crate) fn upload_not_zip(filename: String) -> Error {
+    Error::new(Kind::Upload, Some(UploadError::NotZipArchive { filename }))
+}
+
+pub(crate) fn upload_too_large(filename: String, size: u64, max: u64) -> Error {
+    Error::new(
+        Kind::Upload,
+        Some(UploadError::TooLarge {
+            filename,
+            size,
+            max,
+        }),
+    )
+}
+
+#[cfg(feature = "package")]
+pub(crate) fn upload_package_too_large(filename: String) -> Error {
+    Error::new(
+        Kind::Upload,
+        Some(UploadError::PackageTooLarge { filename }),
+    )
+}
+
+#[cfg(feature = "package")]
+pub(crate) fn upload_package_changed(filename: String) -> Error {
+    Error::new(Kind::Upload, Some(UploadError::PackageChanged { filename }))
+}
+
 pub(crate) fn decode<E: Into<BoxError>>(e: E) -> Error {
     Error::new(Kind::Decode, Some(e))
 }
@@ -258,10 +353,14 @@ pub(crate) fn error_for_status(status: StatusCode, error: ModioError) -> Error {
     }
 }
 
+pub(crate) fn status<E: Into<BoxError>>(status: StatusCode, e: E) -> Error {
+    Error::new(Kind::Status(status), Some(e))
+}
+
 pub(crate) fn ratelimit(reset: u64) -> Error {
     Error::new(
         Kind::RateLimit {
-            reset: Duration::from_secs(reset * 60),
+            reset: Duration::from_secs(reset),
         },
         None::<Error>,
     )
@@ -281,6 +380,21 @@ pub(crate) fn download_no_primary(game_id: u32, mod_id: u32) -> Error {
     )
 }
 
+pub(crate) fn download_no_platform_file(
+    game_id: u32,
+    mod_id: u32,
+    platform: TargetPlatform,
+) -> Error {
+    Error::new(
+        Kind::Download,
+        Some(DownloadError::NoPlatformFile {
+            game_id,
+            mod_id,
+            platform,
+        }),
+    )
+}
+
 pub(crate) fn download_file_not_found(game_id: u32, mod_id: u32, file_id: u32) -> Error {
     Error::new(
         Kind::Download,
@@ -319,3 +433,66 @@ where
         }),
     )
 }
+
+pub(crate) fn download_invalid_version(
+    game_id: u32,
+    mod_id: u32,
+    file_id: u32,
+    version: String,
+) -> Error {
+    Error::new(
+        Kind::Download,
+        Some(DownloadError::InvalidVersion {
+            game_id,
+            mod_id,
+            file_id,
+            version,
+        }),
+    )
+}
+
+pub(crate) fn download_virus_scan(
+    mod_id: u32,
+    file_id: u32,
+    status: VirusStatus,
+    result: VirusResult,
+) -> Error {
+    Error::new(
+        Kind::Download,
+        Some(DownloadError::VirusScan {
+            mod_id,
+            file_id,
+            status,
+            result,
+        }),
+    )
+}
+
+pub(crate) fn download_url_expired(mod_id: u32, file_id: u32) -> Error {
+    Error::new(
+        Kind::Download,
+        Some(DownloadError::UrlExpired { mod_id, file_id }),
+    )
+}
+
+pub(crate) fn download_size_mismatch(file_id: u32, expected: u64, actual: u64) -> Error {
+    Error::new(
+        Kind::Download,
+        Some(DownloadError::SizeMismatch {
+            file_id,
+            expected,
+            actual,
+        }),
+    )
+}
+
+pub(crate) fn download_hash_mismatch(file_id: u32, expected: String, actual: String) -> Error {
+    Error::new(
+        Kind::Download,
+        Some(DownloadError::HashMismatch {
+            file_id,
+            expected,
+            actual,
+        }),
+    )
+}
@@ -0,0 +1,405 @@
+//! Extracting downloaded modfiles.
+//!
+//! Modfiles are zip archives. The [`Extractor`] writes the entries of an archive into a target
+//! directory and rejects entries that would be written outside of it, e.g. entries with absolute
+//! paths, `..` components, symbolic links pointing outside the target directory or entries
+//! below a symbolic link. The size and the number of extracted entries are limited to protect
+//! against zip bombs. If an entry is rejected, the files and directories created for the entries
+//! before it are removed again, files that already existed are kept.
+//!
+//! # Example
+//! ```no_run
+//! use modio::extract::Extractor;
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! #     let modio = modio::Modio::new("api-key")?;
+//!
+//! let extractor = Extractor::new("mods/my-mod")
+//!     .max_size(2 * 1024 * 1024 * 1024)
+//!     .progress(|p| println!("{}/{} entries", p.entries, p.total_entries));
+//!
+//! // Extract a previously downloaded modfile.
+//! modio.download((5, 19)).save_to_file("mod.zip").await?;
+//! extractor.extract_file("mod.zip").await?;
+//!
+//! // Download and extract the modfile.
+//! let file = modio.download((5, 19)).extract_to(&extractor).await?;
+//! #     Ok(())
+//! # }
+//! ```
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tracing::debug;
+use zip::ZipArchive;
+
+use crate::error::{self, Result};
+
+/// Default limit of the total size of the extracted entries, 8 GiB.
+const DEFAULT_MAX_SIZE: u64 = 8 * 1024 * 1024 * 1024;
+/// Default limit of the number of entries.
+const DEFAULT_MAX_ENTRIES: usize = 100_000;
+/// Maximum length of the target of a symbolic link.
+const MAX_SYMLINK_LEN: u64 = 4096;
+
+const S_IFMT: u32 = 0o170_000;
+const S_IFLNK: u32 = 0o120_000;
+
+/// Progress of an extraction passed to the callback of [`Extractor::progress`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ExtractProgress {
+    /// The number of extracted entries.
+    pub entries: usize,
+    /// The number of entries of the archive.
+    pub total_entries: usize,
+    /// The number of extracted bytes.
+    pub extracted: u64,
+}
+
+type Callback = dyn Fn(&ExtractProgress) + Send + Sync;
+
+/// Extracts zip archives into a target directory.
+#[derive(Clone)]
+pub struct Extractor {
+    dir: PathBuf,
+    max_size: u64,
+    max_entries: usize,
+    progress: Option<Arc<Callback>>,
+}
+
+impl Extractor {
+    /// Constructs a new `Extractor` that extracts archives into `dir`.
+    ///
+    /// The directory is created if it doesn't exist.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Extractor {
+        Extractor {
+            dir: dir.into(),
+            max_size: DEFAULT_MAX_SIZE,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            progress: None,
+        }
+    }
+
+    /// Limit the total size of the extracted entries in bytes.
+    ///
+    /// Defaults to 8 GiB.
+    #[must_use]
+    pub fn max_size(self, max_size: u64) -> Extractor {
+        Extractor { max_size, ..self }
+    }
+
+    /// Limit the number of entries of an archive.
+    ///
+    /// Defaults to `100000`.
+    #[must_use]
+    pub fn max_entries(self, max_entries: usize) -> Extractor {
+        Extractor {
+            max_entries,
+            ..self
+        }
+    }
+
+    /// Register a callback that is called with the [`ExtractProgress`] after every entry.
+    #[must_use]
+    pub fn progress<F>(self, f: F) -> Extractor
+    where
+        F: Fn(&ExtractProgress) + Send + Sync + 'static,
+    {
+        Extractor {
+            progress: Some(Arc::new(f)),
+            ..self
+        }
+    }
+
+    /// Returns the target directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Extract the zip archive at `path` into the target directory.
+    pub async fn extract_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref().to_path_buf();
+        let extractor = self.clone();
+        spawn_blocking(move || {
+            let file = fs::File::open(path).map_err(error::decode)?;
+            extractor.extract(io::BufReader::new(file))
+        })
+        .await
+    }
+
+    /// Extract the zip archive from memory into the target directory.
+    pub async fn extract_bytes(&self, bytes: Bytes) -> Result<()> {
+        let extractor = self.clone();
+        spawn_blocking(move || extractor.extract(Cursor::new(bytes))).await
+    }
+
+    fn extract<R: Read + Seek>(&self, reader: R) -> Result<()> {
+        let mut archive = ZipArchive::new(reader).map_err(invalid_archive)?;
+
+        let total_entries = archive.len();
+        if total_entries > self.max_entries {
+            return Err(error::extract(Error::TooManyEntries {
+                limit: self.max_entries,
+            }));
+        }
+
+        // Reject archives whose declared size already exceeds the limit. The actual size of the
+        // entries is checked while writing them.
+        let mut declared = 0u64;
+        for i in 0..total_entries {
+            let entry = archive.by_index_raw(i).map_err(invalid_archive)?;
+            declared = declared.saturating_add(entry.size());
+        }
+        if declared > self.max_size {
+            return Err(error::extract(Error::TooLarge {
+                limit: self.max_size,
+            }));
+        }
+
+        // Remove the extracted entries again if a later entry fails.
+        let mut created = Vec::new();
+        let result = self.extract_entries(&mut archive, &mut created);
+        if result.is_err() {
+            for path in created.iter().rev() {
+                let is_dir = matches!(fs::symlink_metadata(path), Ok(m) if m.is_dir());
+                let _ = if is_dir {
+                    fs::remove_dir(path)
+                } else {
+                    fs::remove_file(path)
+                };
+            }
+        }
+        result
+    }
+
+    /// Extract the entries and record the newly created files and directories in `created`.
+    fn extract_entries<R: Read + Seek>(
+        &self,
+        archive: &mut ZipArchive<R>,
+        created: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        let total_entries = archive.len();
+        create_dirs(&self.dir, created)?;
+
+        let mut extracted = 0u64;
+        for i in 0..total_entries {
+            let mut entry = archive.by_index(i).map_err(invalid_archive)?;
+            let name = entry.name().to_string();
+            let relative = match entry.enclosed_name() {
+                Some(path) if is_relative(path) => path.to_path_buf(),
+                _ => return Err(error::extract(Error::UnsafePath { name })),
+            };
+            // Entries are never written through symbolic links of earlier entries, which
+            // could point anywhere once they are chained.
+            if through_symlink(&self.dir, &relative) {
+                return Err(error::extract(Error::UnsafePath { name }));
+            }
+            let path = self.dir.join(&relative);
+
+            if entry.is_dir() {
+                create_dirs(&path, created)?;
+            } else if matches!(entry.unix_mode(), Some(m) if m & S_IFMT == S_IFLNK) {
+                let mut target = String::new();
+                entry
+                    .by_ref()
+                    .take(MAX_SYMLINK_LEN)
+                    .read_to_string(&mut target)
+                    .map_err(error::decode)?;
+                if escapes(&relative, Path::new(&target)) {
+                    return Err(error::extract(Error::UnsafeSymlink { name, target }));
+                }
+                create_parent(&path, created)?;
+                symlink(&target, &path)?;
+                created.push(path);
+            } else {
+                create_parent(&path, created)?;
+                // Existing files are overwritten but never removed again.
+                let existed = fs::symlink_metadata(&path).is_ok();
+                let mut out = fs::File::create(&path).map_err(error::decode)?;
+                if !existed {
+                    created.push(path);
+                }
+                let remaining = self.max_size - extracted;
+                let written = io::copy(&mut entry.by_ref().take(remaining + 1), &mut out)
+                    .map_err(error::decode)?;
+                if written > remaining {
+                    return Err(error::extract(Error::TooLarge {
+                        limit: self.max_size,
+                    }));
+                }
+                extracted += written;
+            }
+
+            if let Some(progress) = &self.progress {
+                progress(&ExtractProgress {
+                    entries: i + 1,
+                    total_entries,
+                    extracted,
+                });
+            }
+        }
+        debug!(
+            "extracted {} entries into {}",
+            total_entries,
+            self.dir.display()
+        );
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Extractor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extractor")
+            .field("dir", &self.dir)
+            .field("max_size", &self.max_size)
+            .field("max_entries", &self.max_entries)
+            .finish()
+    }
+}
+
+async fn spawn_blocking<F>(f: F) -> Result<()>
+where
+    F: FnOnce() -> Result<()> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(error::decode)?
+}
+
+fn invalid_archive(e: zip::result::ZipError) -> crate::Error {
+    error::extract(Error::InvalidArchive(e.to_string()))
+}
+
+fn is_relative(path: &Path) -> bool {
+    path.components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Returns true if the `target` of the symbolic link at `link` points outside of the
+/// target directory.
+///
+/// `..` is only allowed at the start of the target. A `..` after a symbolic link in the
+/// middle of the target would resolve relative to the target of that link.
+fn escapes(link: &Path, target: &Path) -> bool {
+    let mut depth = link.components().count().saturating_sub(1);
+    let mut descended = false;
+    for c in target.components() {
+        match c {
+            Component::Normal(_) => {
+                depth += 1;
+                descended = true;
+            }
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 && !descended => depth -= 1,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return true,
+        }
+    }
+    false
+}
+
+/// Returns true if the entry or one of its parent directories is a symbolic link.
+fn through_symlink(dir: &Path, relative: &Path) -> bool {
+    let mut path = dir.to_path_buf();
+    relative.components().any(|c| {
+        path.push(c);
+        matches!(fs::symlink_metadata(&path), Ok(m) if m.file_type().is_symlink())
+    })
+}
+
+/// Create the directory and its missing parents and record them in `created`.
+fn create_dirs(path: &Path, created: &mut Vec<PathBuf>) -> Result<()> {
+    let mut missing = Vec::new();
+    let mut current = Some(path);
+    while let Some(dir) = current {
+        if fs::symlink_metadata(dir).is_ok() {
+            break;
+        }
+        missing.push(dir.to_path_buf());
+        current = dir.parent();
+    }
+    fs::create_dir_all(path).map_err(error::decode)?;
+    created.extend(missing.into_iter().rev());
+    Ok(())
+}
+
+fn create_parent(path: &Path, created: &mut Vec<PathBuf>) -> Result<()> {
+    match path.parent() {
+        Some(parent) => create_dirs(parent, created),
+        None => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &str, path: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, path).map_err(error::decode)
+}
+
+#[cfg(not(unix))]
+fn symlink(target: &str, path: &Path) -> Result<()> {
+    debug!(
+        "skipping symbolic link {} -> {}: not supported",
+        path.display(),
+        target
+    );
+    Ok(())
+}
+
+/// The Errors that may occur when extracting a modfile.
+#[derive(Debug)]
+pub enum Error {
+    /// The archive is not a valid zip archive.
+    InvalidArchive(String),
+    /// The entry has an absolute path or a path outside the target directory.
+    UnsafePath { name: String },
+    /// The symbolic link points outside the target directory.
+    UnsafeSymlink { name: String, target: String },
+    /// The extracted entries exceed the size limit.
+    TooLarge { limit: u64 },
+    /// The archive has more entries than allowed.
+    TooManyEntries { limit: usize },
+}
+
+impl StdError for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidArchive(e) => write!(fmt, "invalid zip archive: {}", e),
+            Error::UnsafePath { name } => {
+                write!(fmt, "entry '{}' is outside the target directory.", name)
+            }
+            Error::UnsafeSymlink { name, target } => write!(
+                fmt,
+                "symbolic link '{}' -> '{}' points outside the target directory.",
+                name, target
+            ),
+            Error::TooLarge { limit } => {
+                write!(fmt, "extracted size exceeds the limit of {} bytes.", limit)
+            }
+            Error::TooManyEntries { limit } => {
+                write!(fmt, "archive exceeds the limit of {} entries.", limit)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::escapes;
+
+    #[test]
+    fn symlink_targets() {
+        assert!(!escapes(Path::new("link"), Path::new("file")));
+        assert!(!escapes(Path::new("a/b/link"), Path::new("../../file")));
+        assert!(escapes(Path::new("a/link"), Path::new("../../file")));
+        assert!(escapes(Path::new("link"), Path::new("/etc/passwd")));
+        assert!(escapes(Path::new("link"), Path::new("..")));
+    }
+}
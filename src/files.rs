@@ -1,17 +1,32 @@
 //! Modfile interface
+use std::error::Error as StdError;
 use std::ffi::OsStr;
+use std::fmt;
+use std::io;
 use std::marker::Unpin;
 use std::path::Path;
 
 use mime::APPLICATION_OCTET_STREAM;
 use serde::ser::{Serialize, SerializeMap, Serializer};
-use tokio::io::AsyncRead;
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncReadExt};
 
-use crate::multipart::FileSource;
+use crate::error;
+use crate::multipart::{FileSource, Tracker, UploadOptions, UploadProgress};
 use crate::prelude::*;
-use crate::TargetPlatform;
+use crate::{CancelHandle, TargetPlatform};
 
-pub use crate::types::files::{Download, File, FileHash, Platform, PlatformStatus};
+#[cfg(feature = "package")]
+mod package;
+mod upload;
+
+pub use crate::types::files::{
+    Download, File, FileHash, Platform, PlatformStatus, UploadPart, UploadSession, UploadStatus,
+    VirusResult, VirusScan, VirusStatus,
+};
+#[cfg(feature = "package")]
+pub use package::{Package, PackagedMod};
+pub use upload::{MultipartUpload, DEFAULT_PART_SIZE};
 
 /// Interface for the modfiles of a mod.
 pub struct Files {
@@ -46,18 +61,71 @@ impl Files {
         FileRef::new(self.modio.clone(), self.game, self.mod_id, id)
     }
 
+    /// Create a multipart upload session for a large modfile. [required: token]
+    ///
+    /// The parts of the file are uploaded with [`Files::multipart_upload`].
+    pub async fn create_upload_session<S: Into<String>>(
+        &self,
+        filename: S,
+    ) -> Result<UploadSession> {
+        let route = Route::CreateMultipartUploadSession {
+            game_id: self.game,
+            mod_id: self.mod_id,
+        };
+        self.modio
+            .request(route)
+            .form(&[("filename", filename.into())])
+            .send()
+            .await
+    }
+
+    /// Returns a `Query` interface to retrieve the multipart upload sessions of the mod.
+    /// [required: token]
+    pub fn upload_sessions(&self, filter: Filter) -> Query<UploadSession> {
+        let route = Route::GetMultipartUploadSessions {
+            game_id: self.game,
+            mod_id: self.mod_id,
+        };
+        Query::new(self.modio.clone(), route, filter)
+    }
+
+    /// Return a reference to a multipart upload session to upload the parts of a modfile.
+    pub fn multipart_upload<S: Into<String>>(&self, upload_id: S) -> MultipartUpload {
+        MultipartUpload::new(self.modio.clone(), self.game, self.mod_id, upload_id.into())
+    }
+
     /// Add a file for a mod that this `Files` refers to. [required: token]
+    ///
+    /// Local files are [validated](AddFileOptions::validate) before the upload if enabled and
+    /// refused with an error for which [`Error::is_upload`](crate::Error::is_upload) returns
+    /// true.
     #[allow(clippy::should_implement_trait)]
     pub async fn add(self, options: AddFileOptions) -> Result<File> {
         let route = Route::AddFile {
             game_id: self.game,
             mod_id: self.mod_id,
         };
+        if let (FileData::Source(ref source), Some(max)) = (&options.data, options.validate) {
+            validate_modfile(source, max).await?;
+        }
+        let source = match options.data {
+            FileData::Source(ref source) => Some(source),
+            FileData::Upload(_) => None,
+        };
+        let tracker = options.upload.tracker(source).await;
+        let upload = options.upload.clone();
         self.modio
             .request(route)
-            .multipart(Form::from(options))
+            .multipart(options.into_form(tracker.as_ref()))
             .send()
             .await
+            .map_err(|e| {
+                #[cfg(feature = "package")]
+                if let Some(filename) = package::changed(&e) {
+                    return error::upload_package_changed(filename);
+                }
+                upload.map_err(e)
+            })
     }
 }
 
@@ -175,13 +243,21 @@ pub mod filters {
     filter!(Changelog, CHANGELOG, "changelog", Eq, NotEq, In, Like);
 }
 
+enum FileData {
+    Source(FileSource),
+    Upload(String),
+}
+
 pub struct AddFileOptions {
-    source: FileSource,
+    data: FileData,
     version: Option<String>,
     changelog: Option<String>,
     active: Option<bool>,
     filehash: Option<String>,
     metadata_blob: Option<String>,
+    compute_filehash: bool,
+    validate: Option<u64>,
+    upload: UploadOptions,
 }
 
 impl AddFileOptions {
@@ -191,12 +267,19 @@ impl AddFileOptions {
         S: Into<String>,
     {
         AddFileOptions {
-            source: FileSource::new_from_read(inner, filename.into(), APPLICATION_OCTET_STREAM),
+            data: FileData::Source(FileSource::new_from_read(
+                inner,
+                filename.into(),
+                APPLICATION_OCTET_STREAM,
+            )),
             version: None,
             changelog: None,
             active: None,
             filehash: None,
             metadata_blob: None,
+            compute_filehash: false,
+            validate: None,
+            upload: UploadOptions::default(),
         }
     }
 
@@ -218,12 +301,49 @@ impl AddFileOptions {
         let file = file.as_ref();
 
         AddFileOptions {
-            source: FileSource::new_from_file(file, filename.into(), APPLICATION_OCTET_STREAM),
+            data: FileData::Source(FileSource::new_from_file(
+                file,
+                filename.into(),
+                APPLICATION_OCTET_STREAM,
+            )),
+            version: None,
+            changelog: None,
+            active: None,
+            filehash: None,
+            metadata_blob: None,
+            compute_filehash: false,
+            validate: None,
+            upload: UploadOptions::default(),
+        }
+    }
+
+    #[cfg(feature = "package")]
+    fn with_source(source: FileSource) -> AddFileOptions {
+        AddFileOptions {
+            data: FileData::Source(source),
+            version: None,
+            changelog: None,
+            active: None,
+            filehash: None,
+            metadata_blob: None,
+            compute_filehash: false,
+            validate: None,
+            upload: UploadOptions::default(),
+        }
+    }
+
+    /// Add the file of a [completed](MultipartUpload::complete) multipart upload session.
+    pub fn with_upload<S: Into<String>>(upload_id: S) -> AddFileOptions {
+        AddFileOptions {
+            data: FileData::Upload(upload_id.into()),
             version: None,
             changelog: None,
             active: None,
             filehash: None,
             metadata_blob: None,
+            compute_filehash: false,
+            validate: None,
+            upload: UploadOptions::default(),
         }
     }
 
@@ -232,28 +352,183 @@ impl AddFileOptions {
     option!(active: bool);
     option!(filehash);
     option!(metadata_blob);
-}
 
-#[doc(hidden)]
-impl From<AddFileOptions> for Form {
-    fn from(opts: AddFileOptions) -> Form {
+    /// Compute the MD5 hash of the file while it's uploaded and send it as `filehash`.
+    ///
+    /// The file is read only once, the hash is sent after the file data. Ignored if the hash
+    /// is set with [`filehash`](AddFileOptions::filehash).
+    pub fn compute_filehash(self, enabled: bool) -> Self {
+        Self {
+            compute_filehash: enabled,
+            ..self
+        }
+    }
+
+    /// Validate a local file before the upload. Disabled by default.
+    ///
+    /// The file must be a zip archive no larger than [`DEFAULT_MAX_FILESIZE`]. Files uploaded
+    /// from a reader are not validated, packaged mods are only checked against the size limit.
+    pub fn validate(self, enabled: bool) -> Self {
+        Self {
+            validate: if enabled {
+                Some(DEFAULT_MAX_FILESIZE)
+            } else {
+                None
+            },
+            ..self
+        }
+    }
+
+    /// Set the maximum size of a local file and enable the validation.
+    pub fn max_filesize(self, max: u64) -> Self {
+        Self {
+            validate: Some(max),
+            ..self
+        }
+    }
+
+    /// Report the progress of the file upload to the callback.
+    pub fn progress<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&UploadProgress) + Send + Sync + 'static,
+    {
+        self.upload.progress(f);
+        self
+    }
+
+    /// Stop the file upload once the [`CancelHandle`] is cancelled.
+    ///
+    /// The upload fails with an error for which [`Error::is_cancelled`] returns true.
+    ///
+    /// [`Error::is_cancelled`]: crate::Error::is_cancelled
+    pub fn cancel_handle(mut self, cancel: CancelHandle) -> Self {
+        self.upload.cancel_handle(cancel);
+        self
+    }
+
+    fn into_form(self, tracker: Option<&Tracker>) -> Form {
         let mut form = Form::new();
-        if let Some(version) = opts.version {
+        if let Some(version) = self.version {
             form = form.text("version", version);
         }
-        if let Some(changelog) = opts.changelog {
+        if let Some(changelog) = self.changelog {
             form = form.text("changelog", changelog);
         }
-        if let Some(active) = opts.active {
+        if let Some(active) = self.active {
             form = form.text("active", active.to_string());
         }
-        if let Some(filehash) = opts.filehash {
-            form = form.text("filehash", filehash);
-        }
-        if let Some(metadata_blob) = opts.metadata_blob {
+        let compute_filehash = match self.filehash {
+            Some(filehash) => {
+                form = form.text("filehash", filehash);
+                false
+            }
+            None => self.compute_filehash,
+        };
+        if let Some(metadata_blob) = self.metadata_blob {
             form = form.text("metadata_blob", metadata_blob);
         }
-        form.part("filedata", opts.source.into())
+        match self.data {
+            FileData::Source(source) if compute_filehash => {
+                let (part, filehash) = source.into_hashed_parts(tracker);
+                form.part("filedata", part).part("filehash", filehash)
+            }
+            FileData::Source(source) => form.part("filedata", source.into_part(tracker)),
+            FileData::Upload(upload_id) => form.text("upload_id", upload_id),
+        }
+    }
+}
+
+#[doc(hidden)]
+impl From<AddFileOptions> for Form {
+    fn from(opts: AddFileOptions) -> Form {
+        opts.into_form(None)
+    }
+}
+
+/// The maximum size of a modfile uploaded with [`Files::add`]. Larger files must be uploaded
+/// with a [`MultipartUpload`].
+pub const DEFAULT_MAX_FILESIZE: u64 = 500 * 1024 * 1024;
+
+/// Check that the local file of the source is a zip archive within the size limit.
+async fn validate_modfile(source: &FileSource, max: u64) -> Result<()> {
+    let path = match source.path() {
+        Some(path) => path,
+        None => match source.len().await {
+            Some(size) if size > max => {
+                return Err(error::upload_too_large(source.filename.clone(), size, max));
+            }
+            _ => return Ok(()),
+        },
+    };
+    let mut file = fs::File::open(path).await.map_err(error::decode)?;
+    let size = file.metadata().await.map_err(error::decode)?.len();
+    if size > max {
+        return Err(error::upload_too_large(source.filename.clone(), size, max));
+    }
+    let mut magic = [0; 4];
+    let is_zip = match file.read_exact(&mut magic).await {
+        // Local file header or the end of central directory record of an empty archive.
+        Ok(_) => matches!(&magic, b"PK\x03\x04" | b"PK\x05\x06"),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => false,
+        Err(e) => return Err(error::decode(e)),
+    };
+    if !is_zip {
+        return Err(error::upload_not_zip(source.filename.clone()));
+    }
+    Ok(())
+}
+
+/// The Errors that may occur when a modfile is validated or packaged for the upload with
+/// [`Files::add`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The file is not a zip archive.
+    NotZipArchive { filename: String },
+    /// The file exceeds the maximum file size.
+    TooLarge {
+        filename: String,
+        size: u64,
+        max: u64,
+    },
+    /// The packaged mod exceeds the 4 GiB or 65,535 files of a zip archive without zip64.
+    #[cfg(feature = "package")]
+    PackageTooLarge { filename: String },
+    /// The files of the packaged mod have been modified since the package was built.
+    #[cfg(feature = "package")]
+    PackageChanged { filename: String },
+}
+
+impl StdError for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotZipArchive { filename } => {
+                write!(fmt, "File '{}' is not a zip archive.", filename)
+            }
+            Error::TooLarge {
+                filename,
+                size,
+                max,
+            } => write!(
+                fmt,
+                "File '{}' is too large, {} bytes exceed the limit of {} bytes.",
+                filename, size, max,
+            ),
+            #[cfg(feature = "package")]
+            Error::PackageTooLarge { filename } => write!(
+                fmt,
+                "Package '{}' exceeds the limits of a zip archive without zip64 (4 GiB, 65535 files).",
+                filename,
+            ),
+            #[cfg(feature = "package")]
+            Error::PackageChanged { filename } => write!(
+                fmt,
+                "Package '{}' has been modified since it was built.",
+                filename,
+            ),
+        }
     }
 }
 
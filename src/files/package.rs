@@ -0,0 +1,651 @@
+//! Packaging of mod directories into zip archives.
+use std::convert::TryFrom;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::path::{Component, Path, PathBuf};
+
+use bytes::Bytes;
+use crc32fast::Hasher;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use futures_util::{stream, StreamExt};
+use mime::APPLICATION_OCTET_STREAM;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use super::{AddFileOptions, Error as UploadError};
+use crate::error::{self, Result};
+use crate::multipart::{ByteStream, FileSource};
+
+const LOCAL_FILE_HEADER: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR: u32 = 0x0807_4b50;
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+
+/// Version 2.0 of the zip format, required for deflated entries.
+const VERSION: u16 = 20;
+/// The external attributes of the entries are unix modes.
+const VERSION_MADE_BY: u16 = 3 << 8 | VERSION;
+/// The sizes and the CRC-32 follow the data in a data descriptor and the names are UTF-8.
+const FLAGS: u16 = 1 << 3 | 1 << 11;
+const DEFLATE: u16 = 8;
+/// 1980-01-01 00:00:00, the earliest date of the MS-DOS format.
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = 1 << 5 | 1;
+
+/// Size of the chunks of the archive sent to the upload.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Packages a mod directory into a zip archive for the upload with
+/// [`Files::add`](super::Files::add).
+///
+/// The archive is deterministic: the entries are sorted by their path and have a fixed
+/// timestamp, so the same content always results in the same archive and MD5 hash. The unix
+/// permissions of the files are normalized to `644`, or `755` for executable files.
+///
+/// Archives in the zip64 format aren't supported. The build fails with
+/// [`Error::PackageTooLarge`](super::Error::PackageTooLarge) if the archive exceeds 4 GiB or
+/// 65,535 files.
+///
+/// Patterns of [`include`](Package::include) and [`exclude`](Package::exclude) are matched
+/// against the paths of the files relative to the directory with `/` as separator. `*` matches
+/// any characters except `/`, `?` matches a single character except `/` and `**` matches any
+/// number of directories. Patterns without a `/` are matched against the file names.
+///
+/// # Example
+/// ```no_run
+/// use modio::files::{AddFileOptions, Package};
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// #     let modio = modio::Modio::new("api-key")?;
+///
+/// let package = Package::new("mods/my-mod")
+///     .exclude(".git/**")
+///     .exclude("*.bak")
+///     .build()
+///     .await?;
+/// println!("{} files, md5: {}", package.entries().len(), package.md5());
+///
+/// let options = AddFileOptions::from(package).version("1.2.0");
+/// let file = modio.mod_(5, 19).files().add(options).await?;
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Package {
+    dir: PathBuf,
+    filename: Option<String>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl Package {
+    /// Constructs a new `Package` of the files in `dir`.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Package {
+        Package {
+            dir: dir.into(),
+            filename: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+
+    /// Set the filename of the archive. Defaults to the name of the directory with a `.zip`
+    /// extension.
+    #[must_use]
+    pub fn filename<S: Into<String>>(self, filename: S) -> Self {
+        Self {
+            filename: Some(filename.into()),
+            ..self
+        }
+    }
+
+    /// Add a pattern of the files to package. All files are packaged if no pattern is added.
+    #[must_use]
+    pub fn include<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Add a pattern of the files to leave out of the package.
+    #[must_use]
+    pub fn exclude<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Collect the files of the directory and compute the size and the MD5 hash of the
+    /// archive.
+    ///
+    /// The archive isn't stored, it's written again while it's uploaded. The files must not
+    /// be modified until the upload is finished, otherwise the upload is aborted with
+    /// [`Error::PackageChanged`](super::Error::PackageChanged) as source error.
+    pub async fn build(self) -> Result<PackagedMod> {
+        let filename = match self.filename {
+            Some(ref filename) => filename.clone(),
+            None => match self.dir.file_name().and_then(|n| n.to_str()) {
+                Some(name) => format!("{}.zip", name),
+                None => String::from("mod.zip"),
+            },
+        };
+        let result = tokio::task::spawn_blocking(move || {
+            let entries = self.collect()?;
+            let mut sink = HashWriter {
+                context: md5::Context::new(),
+                len: 0,
+            };
+            write_zip(&entries, &mut sink)?;
+            let md5 = format!("{:x}", sink.context.compute());
+            debug!(
+                "packaged {} files of {:?}: {}",
+                entries.len(),
+                self.dir,
+                md5
+            );
+            Ok::<_, io::Error>((entries, sink.len, md5))
+        })
+        .await
+        .map_err(error::decode)?;
+
+        match result {
+            Ok((entries, size, md5)) => Ok(PackagedMod {
+                filename,
+                entries,
+                size,
+                md5,
+            }),
+            Err(e) if is_limit_exceeded(&e) => Err(error::upload_package_too_large(filename)),
+            Err(e) => Err(error::decode(e)),
+        }
+    }
+
+    /// Returns the included files of the directory sorted by their path in the archive.
+    fn collect(&self) -> io::Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        let mut dirs = vec![self.dir.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(&dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                let file_type = entry.file_type()?;
+                if file_type.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                // Symbolic links to files are packaged with the content of the target.
+                let is_file = file_type.is_file()
+                    || (file_type.is_symlink()
+                        && matches!(fs::metadata(&path), Ok(m) if m.is_file()));
+                if !is_file {
+                    continue;
+                }
+                let name = entry_name(&self.dir, &path)?;
+                if self.is_included(&name) {
+                    let mode = file_mode(&path)?;
+                    entries.push(Entry { name, path, mode });
+                }
+            }
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    fn is_included(&self, name: &str) -> bool {
+        let included = self.include.is_empty() || self.include.iter().any(|p| matches(p, name));
+        included && !self.exclude.iter().any(|p| matches(p, name))
+    }
+}
+
+/// A packaged mod directory, which is uploaded by converting it into [`AddFileOptions`].
+///
+/// The computed MD5 hash is sent as `filehash` of the upload.
+#[derive(Clone, Debug)]
+pub struct PackagedMod {
+    filename: String,
+    entries: Vec<Entry>,
+    size: u64,
+    md5: String,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    name: String,
+    path: PathBuf,
+    /// The unix mode of the file.
+    mode: u32,
+}
+
+impl PackagedMod {
+    /// Returns the filename of the archive.
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// Returns the paths of the packaged files in the archive.
+    pub fn entries(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.entries.iter().map(|e| e.name.as_str())
+    }
+
+    /// Returns the size of the archive.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the MD5 hash of the archive.
+    pub fn md5(&self) -> &str {
+        &self.md5
+    }
+}
+
+impl From<PackagedMod> for AddFileOptions {
+    fn from(package: PackagedMod) -> AddFileOptions {
+        let stream = zip_stream(
+            package.entries,
+            package.filename.clone(),
+            package.size,
+            package.md5.clone(),
+        );
+        let source = FileSource::new_from_stream(
+            stream,
+            package.size,
+            package.filename,
+            APPLICATION_OCTET_STREAM,
+        );
+        AddFileOptions::with_source(source).filehash(package.md5)
+    }
+}
+
+/// Returns the path of the file relative to the directory with `/` as separator.
+fn entry_name(dir: &Path, path: &Path) -> io::Result<String> {
+    let relative = path.strip_prefix(dir).unwrap_or(path);
+    let mut name = String::new();
+    for component in relative.components() {
+        if let Component::Normal(part) = component {
+            let part = part.to_str().ok_or_else(|| {
+                let msg = format!("file name is not valid UTF-8: {:?}", path);
+                io::Error::new(io::ErrorKind::InvalidData, msg)
+            })?;
+            if !name.is_empty() {
+                name.push('/');
+            }
+            name.push_str(part);
+        }
+    }
+    Ok(name)
+}
+
+/// Returns the normalized unix mode of the file: `644`, or `755` if it's executable.
+#[cfg(unix)]
+fn file_mode(path: &Path) -> io::Result<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path)?.permissions().mode();
+    Ok(if mode & 0o111 == 0 {
+        0o100_644
+    } else {
+        0o100_755
+    })
+}
+
+#[cfg(not(unix))]
+fn file_mode(_: &Path) -> io::Result<u32> {
+    Ok(0o100_644)
+}
+
+/// Match the path against the glob pattern. Patterns without a `/` only match the file name.
+fn matches(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = if pattern.contains('/') {
+        (pattern.trim_start_matches('/'), name)
+    } else {
+        (pattern, name.rsplit('/').next().unwrap_or(name))
+    };
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    glob(&pattern, &name)
+}
+
+fn glob(pattern: &[char], name: &[char]) -> bool {
+    match pattern {
+        [] => name.is_empty(),
+        // `**/` matches zero or more directories.
+        ['*', '*', '/', rest @ ..] => {
+            glob(rest, name)
+                || (0..name.len()).any(|i| name[i] == '/' && glob(rest, &name[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=name.len()).any(|i| glob(rest, &name[i..])),
+        ['*', rest @ ..] => (0..=name.len())
+            .take_while(|&i| i == 0 || name[i - 1] != '/')
+            .any(|i| glob(rest, &name[i..])),
+        ['?', rest @ ..] => matches!(name, [c, tail @ ..] if *c != '/' && glob(rest, tail)),
+        [p, rest @ ..] => matches!(name, [c, tail @ ..] if c == p && glob(rest, tail)),
+    }
+}
+
+/// Stream of the archive, which is written on a blocking thread while the stream is polled.
+///
+/// The last chunk is held back until the size and the MD5 hash of the archive are checked, so
+/// the upload is aborted if the files have changed since the package was built.
+fn zip_stream(entries: Vec<Entry>, filename: String, size: u64, md5: String) -> ByteStream {
+    let stream = stream::once(async move {
+        let (tx, mut rx) = mpsc::channel(4);
+        tokio::task::spawn_blocking(move || {
+            let mut writer = ChannelWriter {
+                tx: tx.clone(),
+                buf: Vec::with_capacity(CHUNK_SIZE),
+                hash: HashWriter {
+                    context: md5::Context::new(),
+                    len: 0,
+                },
+                filename,
+                size,
+            };
+            let result = write_zip(&entries, &mut writer).and_then(|_| writer.finish(&md5));
+            if let Err(e) = result {
+                // The upload has stopped if the receiver is closed.
+                let _ = tx.blocking_send(Err(e));
+            }
+        });
+        stream::poll_fn(move |cx| rx.poll_recv(cx))
+    });
+    Box::pin(stream.flatten())
+}
+
+/// Write the deterministic zip archive of the entries.
+fn write_zip<W: Write>(entries: &[Entry], out: W) -> io::Result<()> {
+    let count = to_u16(entries.len())?;
+    let mut out = CountingWriter {
+        inner: out,
+        count: 0,
+    };
+    let mut central = Vec::new();
+    let mut buf = vec![0; CHUNK_SIZE];
+    for entry in entries {
+        let offset = out.count;
+        let name = entry.name.as_bytes();
+        let name_len = to_u16(name.len())?;
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        put_u32(&mut header, LOCAL_FILE_HEADER);
+        put_u16(&mut header, VERSION);
+        put_u16(&mut header, FLAGS);
+        put_u16(&mut header, DEFLATE);
+        put_u16(&mut header, DOS_TIME);
+        put_u16(&mut header, DOS_DATE);
+        // CRC-32, compressed and uncompressed size are written to the data descriptor.
+        header.extend_from_slice(&[0; 12]);
+        put_u16(&mut header, name_len);
+        put_u16(&mut header, 0);
+        header.extend_from_slice(name);
+        out.write_all(&header)?;
+
+        let start = out.count;
+        let mut crc = Hasher::new();
+        let mut size = 0;
+        let mut file = fs::File::open(&entry.path)?;
+        let mut encoder = DeflateEncoder::new(&mut out, Compression::default());
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            crc.update(&buf[..n]);
+            encoder.write_all(&buf[..n])?;
+            size += n as u64;
+        }
+        encoder.finish()?;
+        let crc = crc.finalize();
+        let compressed = to_u32(out.count - start)?;
+        let size = to_u32(size)?;
+
+        let mut descriptor = Vec::with_capacity(16);
+        put_u32(&mut descriptor, DATA_DESCRIPTOR);
+        put_u32(&mut descriptor, crc);
+        put_u32(&mut descriptor, compressed);
+        put_u32(&mut descriptor, size);
+        out.write_all(&descriptor)?;
+
+        put_u32(&mut central, CENTRAL_DIRECTORY_HEADER);
+        put_u16(&mut central, VERSION_MADE_BY);
+        put_u16(&mut central, VERSION);
+        put_u16(&mut central, FLAGS);
+        put_u16(&mut central, DEFLATE);
+        put_u16(&mut central, DOS_TIME);
+        put_u16(&mut central, DOS_DATE);
+        put_u32(&mut central, crc);
+        put_u32(&mut central, compressed);
+        put_u32(&mut central, size);
+        put_u16(&mut central, name_len);
+        // Extra field length, comment length, disk number and internal attributes.
+        central.extend_from_slice(&[0; 8]);
+        put_u32(&mut central, entry.mode << 16);
+        put_u32(&mut central, to_u32(offset)?);
+        central.extend_from_slice(name);
+    }
+    let offset = to_u32(out.count)?;
+    out.write_all(&central)?;
+
+    let mut end = Vec::with_capacity(22);
+    put_u32(&mut end, END_OF_CENTRAL_DIRECTORY);
+    // Number of the disk and the disk with the central directory.
+    put_u32(&mut end, 0);
+    put_u16(&mut end, count);
+    put_u16(&mut end, count);
+    put_u32(&mut end, to_u32(central.len() as u64)?);
+    put_u32(&mut end, offset);
+    put_u16(&mut end, 0);
+    out.write_all(&end)
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn to_u16(value: usize) -> io::Result<u16> {
+    u16::try_from(value).map_err(|_| limit_exceeded())
+}
+
+fn to_u32(value: u64) -> io::Result<u32> {
+    u32::try_from(value).map_err(|_| limit_exceeded())
+}
+
+/// The archive exceeds the size or the number of entries of a zip archive without zip64.
+#[derive(Debug)]
+struct LimitExceeded;
+
+impl StdError for LimitExceeded {}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("package exceeds the limits of a zip archive without zip64")
+    }
+}
+
+fn limit_exceeded() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, LimitExceeded)
+}
+
+fn is_limit_exceeded(err: &io::Error) -> bool {
+    matches!(err.get_ref(), Some(e) if e.is::<LimitExceeded>())
+}
+
+struct CountingWriter<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Computes the size and the MD5 hash of the written bytes.
+struct HashWriter {
+    context: md5::Context,
+    len: u64,
+}
+
+impl Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.context.consume(buf);
+        self.len += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Find the [`UploadError::PackageChanged`] error that aborted the upload of a package.
+pub(super) fn changed(err: &crate::Error) -> Option<String> {
+    let mut source = StdError::source(err);
+    while let Some(err) = source {
+        // `io::Error::source` skips the wrapped error.
+        let inner = err
+            .downcast_ref::<io::Error>()
+            .and_then(io::Error::get_ref)
+            .map_or(err, |inner| inner as &(dyn StdError + 'static));
+        if let Some(UploadError::PackageChanged { filename }) = inner.downcast_ref() {
+            return Some(filename.clone());
+        }
+        source = err.source();
+    }
+    None
+}
+
+/// Sends the written bytes in chunks to the stream of the upload.
+///
+/// A full chunk is sent once more bytes are written, so the last written bytes stay in the
+/// buffer until they're sent with [`ChannelWriter::send`].
+struct ChannelWriter {
+    tx: mpsc::Sender<io::Result<Bytes>>,
+    buf: Vec<u8>,
+    hash: HashWriter,
+    filename: String,
+    /// The size of the archive when the package was built.
+    size: u64,
+}
+
+impl ChannelWriter {
+    /// Send the last chunk if the archive matches the size and the MD5 hash of the package.
+    fn finish(mut self, md5: &str) -> io::Result<()> {
+        let hash = format!("{:x}", self.hash.context.clone().compute());
+        if self.hash.len != self.size || hash != md5 {
+            return Err(self.changed());
+        }
+        self.send()
+    }
+
+    fn changed(&self) -> io::Error {
+        let filename = self.filename.clone();
+        let err = UploadError::PackageChanged { filename };
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+
+    fn send(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE));
+        self.tx
+            .blocking_send(Ok(Bytes::from(chunk)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "upload stopped"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buf.len() >= CHUNK_SIZE {
+            self.send()?;
+        }
+        self.hash.write_all(buf)?;
+        if self.hash.len > self.size {
+            return Err(self.changed());
+        }
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::path::PathBuf;
+
+    use super::{file_mode, is_limit_exceeded, matches, write_zip, Entry};
+
+    #[test]
+    fn glob_patterns() {
+        assert!(matches("*.lua", "init.lua"));
+        assert!(matches("*.lua", "scripts/init.lua"));
+        assert!(!matches("*.lua", "init.luac"));
+        assert!(matches("scripts/*.lua", "scripts/init.lua"));
+        assert!(!matches("scripts/*.lua", "scripts/lib/init.lua"));
+        assert!(matches("scripts/**/*.lua", "scripts/init.lua"));
+        assert!(matches("scripts/**/*.lua", "scripts/lib/init.lua"));
+        assert!(matches(".git/**", ".git/objects/ab/cdef"));
+        assert!(matches("/assets/?.png", "assets/a.png"));
+        assert!(!matches("assets/?.png", "assets/ab.png"));
+        assert!(!matches("assets/*", "other/assets/a.png"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn unix_modes() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.sh");
+        std::fs::write(&path, b"").unwrap();
+        let mode = |mode| std::fs::Permissions::from_mode(mode);
+
+        std::fs::set_permissions(&path, mode(0o600)).unwrap();
+        assert_eq!(file_mode(&path).unwrap(), 0o100_644);
+        std::fs::set_permissions(&path, mode(0o700)).unwrap();
+        assert_eq!(file_mode(&path).unwrap(), 0o100_755);
+
+        let entries = [Entry {
+            name: String::from("run.sh"),
+            mode: file_mode(&path).unwrap(),
+            path,
+        }];
+        let mut archive = Vec::new();
+        write_zip(&entries, &mut archive).unwrap();
+        // The external attributes of the central directory header.
+        let central = archive.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        assert_eq!(archive[central + 5], 3);
+        assert_eq!(
+            archive[central + 38..central + 42],
+            (0o100_755u32 << 16).to_le_bytes()
+        );
+    }
+
+    #[test]
+    fn zip64_limits() {
+        let entry = |i| Entry {
+            name: format!("{}", i),
+            path: PathBuf::from(format!("{}", i)),
+            mode: 0o100_644,
+        };
+        let entries = (0..=u16::MAX as usize).map(entry).collect::<Vec<_>>();
+        let err = write_zip(&entries, io::sink()).unwrap_err();
+        assert!(is_limit_exceeded(&err));
+    }
+}
@@ -0,0 +1,192 @@
+//! Multipart upload sessions for large modfiles.
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::path::Path;
+
+use bytes::Bytes;
+use http::header::{CONTENT_RANGE, CONTENT_TYPE};
+use tokio::fs::File as AsyncFile;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tracing::debug;
+
+use crate::error;
+use crate::filter::{custom_filter, Operator};
+use crate::prelude::*;
+use crate::retry::RetryPolicy;
+use crate::types::files::{UploadPart, UploadSession};
+
+/// The default size of the uploaded parts, which is the maximum part size accepted by mod.io.
+pub const DEFAULT_PART_SIZE: u64 = 50 * 1024 * 1024;
+
+/// Upload of a modfile in numbered parts to a multipart upload session.
+///
+/// Constructed with [`Files::multipart_upload`](super::Files::multipart_upload) from the id of
+/// a session created with [`Files::create_upload_session`](super::Files::create_upload_session).
+/// The id can be saved to resume an interrupted upload later, the parts that were already
+/// uploaded are skipped. A resumed upload must use the same part size.
+///
+/// # Example
+/// ```no_run
+/// use modio::files::AddFileOptions;
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// #     let modio = modio::Modio::new("api-key")?;
+/// let files = modio.mod_(5, 19).files();
+///
+/// let session = files.create_upload_session("mod.zip").await?;
+/// // Save `session.upload_id` to resume the upload after a restart.
+///
+/// files
+///     .multipart_upload(&session.upload_id)
+///     .upload_file("mod.zip")
+///     .await?;
+///
+/// let options = AddFileOptions::with_upload(session.upload_id).version("1.2.0");
+/// let file = files.add(options).await?;
+/// #     Ok(())
+/// # }
+/// ```
+pub struct MultipartUpload {
+    modio: Modio,
+    game: u32,
+    mod_id: u32,
+    upload_id: String,
+    part_size: u64,
+    retry: RetryPolicy,
+}
+
+impl MultipartUpload {
+    pub(crate) fn new(modio: Modio, game: u32, mod_id: u32, upload_id: String) -> Self {
+        Self {
+            modio,
+            game,
+            mod_id,
+            upload_id,
+            part_size: DEFAULT_PART_SIZE,
+            retry: RetryPolicy::new(3),
+        }
+    }
+
+    /// Returns the id of the upload session.
+    pub fn upload_id(&self) -> &str {
+        &self.upload_id
+    }
+
+    /// Set the size of the uploaded parts. Defaults to [`DEFAULT_PART_SIZE`].
+    #[must_use]
+    pub fn part_size(self, part_size: u64) -> Self {
+        Self {
+            part_size: part_size.max(1),
+            ..self
+        }
+    }
+
+    /// Set the [`RetryPolicy`] for the upload of a single part.
+    ///
+    /// The policy replaces the retry policy of the client for the part requests.
+    /// Defaults to `RetryPolicy::new(3)`. A retried part is uploaded again from the start.
+    #[must_use]
+    pub fn retry(self, policy: RetryPolicy) -> Self {
+        Self {
+            retry: policy,
+            ..self
+        }
+    }
+
+    /// Returns a `Query` interface to retrieve the parts uploaded to the session.
+    /// [required: token]
+    pub fn parts(&self) -> Query<UploadPart> {
+        let route = Route::GetMultipartUploadParts {
+            game_id: self.game,
+            mod_id: self.mod_id,
+        };
+        let filter = custom_filter("upload_id", Operator::Equals, &self.upload_id);
+        Query::new(self.modio.clone(), route, filter)
+    }
+
+    /// Upload the parts of the local file that are missing in the session and complete the
+    /// session. [required: token]
+    pub async fn upload_file<P: AsRef<Path>>(&self, file: P) -> Result<UploadSession> {
+        let mut file = AsyncFile::open(file).await.map_err(error::decode)?;
+        let total = file.metadata().await.map_err(error::decode)?.len();
+
+        let uploaded = self
+            .parts()
+            .collect()
+            .await?
+            .into_iter()
+            .map(|p| (p.part_number, p.part_size))
+            .collect::<HashMap<_, _>>();
+
+        // `u64::div_ceil` requires Rust 1.73.
+        #[allow(clippy::manual_div_ceil)]
+        let count = (total + self.part_size - 1) / self.part_size;
+        for number in 1..=count {
+            let start = (number - 1) * self.part_size;
+            let len = self.part_size.min(total - start);
+            let number = number as u32;
+            if uploaded.get(&number) == Some(&len) {
+                debug!(
+                    "part {} of upload {} already uploaded",
+                    number, self.upload_id
+                );
+                continue;
+            }
+
+            file.seek(SeekFrom::Start(start))
+                .await
+                .map_err(error::decode)?;
+            let mut buf = vec![0; len as usize];
+            file.read_exact(&mut buf).await.map_err(error::decode)?;
+            self.upload_part(Bytes::from(buf), start, total).await?;
+        }
+        self.complete().await
+    }
+
+    /// Upload the bytes `start..start + bytes.len()` of a file with a size of `total` bytes
+    /// and retry the part according to the retry policy.
+    async fn upload_part(&self, bytes: Bytes, start: u64, total: u64) -> Result<UploadPart> {
+        let end = start + bytes.len() as u64 - 1;
+        let route = Route::AddMultipartUploadPart {
+            game_id: self.game,
+            mod_id: self.mod_id,
+        };
+        self.modio
+            .request(route)
+            .query(&[("upload_id", &self.upload_id)])
+            .header(CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, total))
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .body(bytes)
+            .retry(self.retry.clone())
+            .send()
+            .await
+    }
+
+    /// Complete the session after all parts are uploaded. [required: token]
+    ///
+    /// The completed session can be added as modfile with
+    /// [`AddFileOptions::with_upload`](super::AddFileOptions::with_upload).
+    pub async fn complete(&self) -> Result<UploadSession> {
+        let route = Route::CompleteMultipartUploadSession {
+            game_id: self.game,
+            mod_id: self.mod_id,
+        };
+        self.modio
+            .request(route)
+            .query(&[("upload_id", &self.upload_id)])
+            .send()
+            .await
+    }
+
+    /// Cancel the session and delete its uploaded parts. [required: token]
+    pub async fn cancel(&self) -> Result<()> {
+        let route = Route::DeleteMultipartUploadSession {
+            game_id: self.game,
+            mod_id: self.mod_id,
+        };
+        self.modio
+            .request(route)
+            .query(&[("upload_id", &self.upload_id)])
+            .send()
+            .await
+    }
+}
@@ -410,9 +410,11 @@ impl Filter {
     }
 
     #[must_use]
-    pub fn and(self, mut other: Filter) -> Filter {
+    pub fn and(self, other: Filter) -> Filter {
         let Filter { mut filters, .. } = self;
-        filters.append(&mut other.filters);
+        for entry in other.filters {
+            filters.replace(entry);
+        }
         Filter {
             filters,
             order_by: other.order_by.or(self.order_by),
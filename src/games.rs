@@ -5,8 +5,9 @@ use std::path::Path;
 use mime::IMAGE_STAR;
 
 use crate::mods::{ModRef, Mods};
-use crate::multipart::FileSource;
+use crate::multipart::{FileSource, Tracker, UploadOptions, UploadProgress};
 use crate::prelude::*;
+use crate::CancelHandle;
 
 pub use crate::types::games::{
     ApiAccessOptions, CommunityOptions, CurationOption, Downloads, Game, HeaderImage, Icon,
@@ -80,11 +81,14 @@ impl GameRef {
     /// Add new media to a game. [required: token]
     pub async fn edit_media(self, media: EditMediaOptions) -> Result<()> {
         let route = Route::AddGameMedia { game_id: self.id };
+        let tracker = media.upload.tracker(media.sources()).await;
+        let upload = media.upload.clone();
         self.modio
             .request(route)
-            .multipart(Form::from(media))
+            .multipart(media.into_form(tracker.as_ref()))
             .send::<Message>()
-            .await?;
+            .await
+            .map_err(|e| upload.map_err(e))?;
         Ok(())
     }
 }
@@ -329,6 +333,7 @@ pub struct EditMediaOptions {
     logo: Option<FileSource>,
     icon: Option<FileSource>,
     header: Option<FileSource>,
+    upload: UploadOptions,
 }
 
 impl EditMediaOptions {
@@ -373,21 +378,50 @@ impl EditMediaOptions {
             ..self
         }
     }
-}
 
-#[doc(hidden)]
-impl From<EditMediaOptions> for Form {
-    fn from(opts: EditMediaOptions) -> Form {
+    /// Report the progress of the media upload to the callback.
+    #[must_use]
+    pub fn progress<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&UploadProgress) + Send + Sync + 'static,
+    {
+        self.upload.progress(f);
+        self
+    }
+
+    /// Stop the media upload once the [`CancelHandle`] is cancelled.
+    ///
+    /// The upload fails with an error for which [`Error::is_cancelled`] returns true.
+    ///
+    /// [`Error::is_cancelled`]: crate::Error::is_cancelled
+    #[must_use]
+    pub fn cancel_handle(mut self, cancel: CancelHandle) -> Self {
+        self.upload.cancel_handle(cancel);
+        self
+    }
+
+    fn sources(&self) -> impl Iterator<Item = &FileSource> {
+        self.logo.iter().chain(&self.icon).chain(&self.header)
+    }
+
+    fn into_form(self, tracker: Option<&Tracker>) -> Form {
         let mut form = Form::new();
-        if let Some(logo) = opts.logo {
-            form = form.part("logo", logo.into());
+        if let Some(logo) = self.logo {
+            form = form.part("logo", logo.into_part(tracker));
         }
-        if let Some(icon) = opts.icon {
-            form = form.part("icon", icon.into());
+        if let Some(icon) = self.icon {
+            form = form.part("icon", icon.into_part(tracker));
         }
-        if let Some(header) = opts.header {
-            form = form.part("header", header.into());
+        if let Some(header) = self.header {
+            form = form.part("header", header.into_part(tracker));
         }
         form
     }
 }
+
+#[doc(hidden)]
+impl From<EditMediaOptions> for Form {
+    fn from(opts: EditMediaOptions) -> Form {
+        opts.into_form(None)
+    }
+}
@@ -10,9 +10,9 @@
 //! - Request an [API key (Read-only)](https://mod.io/apikey)
 //! - Manually create an [OAuth 2 Access Token (Read + Write)](https://mod.io/oauth)
 //! - [Email Authentication Flow](auth/struct.Auth.html#example) to create an OAuth 2 Access Token
-//! (Read + Write)
+//!   (Read + Write)
 //! - [External Authentication](auth::Auth::external) to create an OAuth 2 Access Token (Read + Write)
-//! automatically on platforms such as Steam, GOG, itch.io, Switch, Xbox, Discord and Oculus.
+//!   automatically on platforms such as Steam, GOG, itch.io, Switch, Xbox, Discord and Oculus.
 //!
 //! # Rate Limiting
 //!
@@ -23,6 +23,20 @@
 //! [`Error::is_ratelimited`] will return true
 //! if the rate limit associated with credentials has been exhausted.
 //!
+//! Failed requests can be retried automatically, see [`Builder::retry`] and the
+//! [`retry`] module. [`Builder::ratelimit_budget`] keeps track of the remaining requests and
+//! delays requests before the rate limit is exhausted.
+//!
+//! # Optional Features
+//!
+//! - `extract`: Extract downloaded modfiles with `extract::Extractor` or
+//!   `Downloader::extract_to`.
+//! - `package`: Package a mod directory into a zip archive for the upload with
+//!   `files::Package`.
+//! - `publish`: Publish mods from a TOML or JSON manifest with `publish::Publisher`.
+//! - `tower`: Build the request pipeline as a `tower::Service` stack with
+//!   `Builder::layer` to add timeouts, concurrency limits or metrics.
+//!
 //! # Example: Basic setup
 //!
 //! ```no_run
@@ -106,6 +120,15 @@
 //!     policy: ResolvePolicy::Latest,
 //! };
 //! modio.download(action).save_to_file("mod.zip").await?;
+//!
+//! // Download the file with the highest version matching a semver requirement.
+//! let action = DownloadAction::VersionReq {
+//!     game_id: 5,
+//!     mod_id: 19,
+//!     req: "^1.4".parse()?,
+//!     policy: ResolvePolicy::Latest,
+//! };
+//! modio.download(action).save_to_file("mod.zip").await?;
 //! #    Ok(())
 //! # }
 //! ```
@@ -122,27 +145,40 @@ pub mod auth;
 pub mod filter;
 pub mod comments;
 pub mod download;
+#[cfg(feature = "extract")]
+pub mod extract;
 pub mod files;
 pub mod games;
 pub mod metadata;
 pub mod mods;
+#[cfg(feature = "publish")]
+pub mod publish;
 pub mod reports;
+pub mod retry;
 pub mod teams;
+pub mod transport;
 pub mod user;
 
+mod cancel;
 mod client;
 mod error;
 mod loader;
 mod multipart;
+mod ratelimit;
+mod redact;
 mod request;
+mod response;
 mod routing;
 mod types;
 
 pub use crate::auth::Credentials;
+pub use crate::cancel::CancelHandle;
 pub use crate::client::{Builder, Modio};
 pub use crate::download::DownloadAction;
 pub use crate::error::{Error, Result};
 pub use crate::loader::{Page, Query};
+pub use crate::multipart::UploadProgress;
+pub use crate::response::ResponseMeta;
 pub use crate::types::{Deletion, Editing, TargetPlatform, TargetPortal};
 
 mod prelude {
@@ -9,9 +9,10 @@ use crate::comments::Comments;
 use crate::error::Kind;
 use crate::files::{FileRef, Files};
 use crate::metadata::Metadata;
-use crate::multipart::FileSource;
+use crate::multipart::{FileSource, Tracker, UploadOptions, UploadProgress};
 use crate::prelude::*;
 use crate::teams::Members;
+use crate::CancelHandle;
 
 pub use crate::types::mods::{
     Dependency, Event, EventType, Image, MaturityOption, Media, Mod, Platform, Popularity, Ratings,
@@ -173,11 +174,14 @@ impl ModRef {
             game_id: self.game,
             mod_id: self.id,
         };
+        let tracker = options.upload.tracker(options.sources()).await;
+        let upload = options.upload.clone();
         self.modio
             .request(route)
-            .multipart(Form::from(options))
+            .multipart(options.into_form(tracker.as_ref()))
             .send::<Message>()
-            .await?;
+            .await
+            .map_err(|e| upload.map_err(e))?;
 
         Ok(())
     }
@@ -764,6 +768,7 @@ pub struct AddMediaOptions {
     images: Option<Vec<FileSource>>,
     youtube: Option<Vec<String>>,
     sketchfab: Option<Vec<String>>,
+    upload: UploadOptions,
 }
 
 impl AddMediaOptions {
@@ -829,29 +834,52 @@ impl AddMediaOptions {
             ..self
         }
     }
-}
 
-#[doc(hidden)]
-impl From<AddMediaOptions> for Form {
-    fn from(opts: AddMediaOptions) -> Form {
+    /// Report the progress of the media upload to the callback.
+    #[must_use]
+    pub fn progress<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&UploadProgress) + Send + Sync + 'static,
+    {
+        self.upload.progress(f);
+        self
+    }
+
+    /// Stop the media upload once the [`CancelHandle`] is cancelled.
+    ///
+    /// The upload fails with an error for which [`Error::is_cancelled`] returns true.
+    ///
+    /// [`Error::is_cancelled`]: crate::Error::is_cancelled
+    #[must_use]
+    pub fn cancel_handle(mut self, cancel: CancelHandle) -> Self {
+        self.upload.cancel_handle(cancel);
+        self
+    }
+
+    fn sources(&self) -> impl Iterator<Item = &FileSource> {
+        let images = self.images.iter().flatten();
+        self.logo.iter().chain(&self.images_zip).chain(images)
+    }
+
+    fn into_form(self, tracker: Option<&Tracker>) -> Form {
         let mut form = Form::new();
-        if let Some(logo) = opts.logo {
-            form = form.part("logo", logo.into());
+        if let Some(logo) = self.logo {
+            form = form.part("logo", logo.into_part(tracker));
         }
-        if let Some(zip) = opts.images_zip {
-            form = form.part("images", zip.into());
+        if let Some(zip) = self.images_zip {
+            form = form.part("images", zip.into_part(tracker));
         }
-        if let Some(images) = opts.images {
+        if let Some(images) = self.images {
             for (i, image) in images.into_iter().enumerate() {
-                form = form.part(format!("image{}", i), image.into());
+                form = form.part(format!("image{}", i), image.into_part(tracker));
             }
         }
-        if let Some(youtube) = opts.youtube {
+        if let Some(youtube) = self.youtube {
             for url in youtube {
                 form = form.text("youtube[]", url);
             }
         }
-        if let Some(sketchfab) = opts.sketchfab {
+        if let Some(sketchfab) = self.sketchfab {
             for url in sketchfab {
                 form = form.text("sketchfab[]", url);
             }
@@ -860,6 +888,13 @@ impl From<AddMediaOptions> for Form {
     }
 }
 
+#[doc(hidden)]
+impl From<AddMediaOptions> for Form {
+    fn from(opts: AddMediaOptions) -> Form {
+        opts.into_form(None)
+    }
+}
+
 #[derive(Default)]
 pub struct DeleteMediaOptions {
     images: Option<Vec<String>>,
@@ -1,29 +1,45 @@
+use std::fmt;
+use std::io;
 use std::marker::Unpin;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
 
-use futures_util::TryFutureExt;
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_util::{stream, FutureExt, TryFutureExt};
 use mime::Mime;
+use pin_project_lite::pin_project;
 use reqwest::multipart::Part;
 use reqwest::Body;
-use tokio::fs::File;
+use tokio::fs::{self, File};
 use tokio::io::AsyncRead;
+use tokio::sync::oneshot;
 use tokio_util::io::ReaderStream;
 
+use crate::cancel::CancelHandle;
+use crate::error::{self, Error};
+
 pub struct FileSource {
-    pub body: Body,
+    source: Source,
     pub filename: String,
     pub mime: Mime,
 }
 
+enum Source {
+    File(PathBuf),
+    Read(Box<dyn AsyncRead + Send + Sync + Unpin>),
+    #[cfg_attr(not(feature = "package"), allow(dead_code))]
+    Stream(ByteStream, u64),
+}
+
 impl FileSource {
     pub fn new_from_file<P: AsRef<Path>>(file: P, filename: String, mime: Mime) -> Self {
-        let file = file.as_ref().to_path_buf();
-        let st = File::open(file)
-            .map_ok(ReaderStream::new)
-            .try_flatten_stream();
-
         FileSource {
-            body: Body::wrap_stream(st),
+            source: Source::File(file.as_ref().to_path_buf()),
             filename,
             mime,
         }
@@ -34,18 +50,294 @@ impl FileSource {
         T: AsyncRead + Send + Sync + Unpin + 'static,
     {
         FileSource {
-            body: Body::wrap_stream(ReaderStream::new(read)),
+            source: Source::Read(Box::new(read)),
             filename,
             mime,
         }
     }
+
+    /// Constructs a source from a stream of `len` bytes.
+    #[cfg(feature = "package")]
+    pub fn new_from_stream(stream: ByteStream, len: u64, filename: String, mime: Mime) -> Self {
+        FileSource {
+            source: Source::Stream(stream, len),
+            filename,
+            mime,
+        }
+    }
+
+    /// Returns the size of the file or `None` for readers.
+    pub async fn len(&self) -> Option<u64> {
+        match self.source {
+            Source::File(ref path) => fs::metadata(path).await.ok().map(|m| m.len()),
+            Source::Read(_) => None,
+            Source::Stream(_, len) => Some(len),
+        }
+    }
+
+    /// Returns the path of the file or `None` for readers and streams.
+    pub fn path(&self) -> Option<&Path> {
+        match self.source {
+            Source::File(ref path) => Some(path),
+            Source::Read(_) | Source::Stream(..) => None,
+        }
+    }
+
+    /// Convert the source into a part of a multipart form which reports the bytes read from
+    /// the source to the `tracker`.
+    pub fn into_part(self, tracker: Option<&Tracker>) -> Part {
+        let stream = self.source.into_stream();
+        into_part(stream, tracker, self.filename, &self.mime)
+    }
+
+    /// Convert the source into a part of a multipart form and a second part with the MD5 hash
+    /// of the source, which is computed while the first part is sent.
+    pub fn into_hashed_parts(self, tracker: Option<&Tracker>) -> (Part, Part) {
+        let (tx, rx) = oneshot::channel();
+        let stream = Box::pin(Hashed::new(self.source.into_stream(), tx));
+        let part = into_part(stream, tracker, self.filename, &self.mime);
+
+        let hash = rx.map(|hash| {
+            hash.map(Bytes::from).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "file stream failed before its end",
+                )
+            })
+        });
+        let hash = Part::stream(Body::wrap_stream(stream::once(hash)));
+        (part, hash)
+    }
+}
+
+pub type ByteStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send + Sync>>;
+
+impl Source {
+    fn into_stream(self) -> ByteStream {
+        match self {
+            Source::File(path) => Box::pin(
+                File::open(path)
+                    .map_ok(ReaderStream::new)
+                    .try_flatten_stream(),
+            ),
+            Source::Read(read) => Box::pin(ReaderStream::new(read)),
+            Source::Stream(stream, _) => stream,
+        }
+    }
+}
+
+fn into_part(stream: ByteStream, tracker: Option<&Tracker>, filename: String, mime: &Mime) -> Part {
+    let body = match tracker {
+        Some(tracker) => Body::wrap_stream(Tracked::new(stream, tracker.clone())),
+        None => Body::wrap_stream(stream),
+    };
+    Part::stream(body)
+        .file_name(filename)
+        .mime_str(mime.as_ref())
+        .expect("FileSource::into::<Part>()")
 }
 
 impl From<FileSource> for Part {
     fn from(source: FileSource) -> Part {
-        Part::stream(source.body)
-            .file_name(source.filename)
-            .mime_str(source.mime.as_ref())
-            .expect("FileSource::into::<Part>()")
+        source.into_part(None)
+    }
+}
+
+/// Progress of an upload passed to the callback set with the `progress` method of the upload
+/// options, e.g. [`AddFileOptions::progress`](crate::files::AddFileOptions::progress).
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct UploadProgress {
+    /// The number of bytes read from the uploaded files.
+    pub uploaded: u64,
+    /// The total size of the uploaded files, `None` if a file is uploaded from a reader.
+    pub total: Option<u64>,
+    /// The average upload speed in bytes per second.
+    pub speed: f64,
+}
+
+type Callback = dyn Fn(&UploadProgress) + Send + Sync;
+
+#[derive(Clone)]
+struct ProgressFn(Arc<Callback>);
+
+impl fmt::Debug for ProgressFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressFn")
+    }
+}
+
+/// Progress callback and cancel handle of the upload options.
+#[derive(Clone, Debug, Default)]
+pub struct UploadOptions {
+    progress: Option<ProgressFn>,
+    cancel: Option<CancelHandle>,
+}
+
+impl UploadOptions {
+    pub fn progress<F>(&mut self, f: F)
+    where
+        F: Fn(&UploadProgress) + Send + Sync + 'static,
+    {
+        self.progress = Some(ProgressFn(Arc::new(f)));
+    }
+
+    pub fn cancel_handle(&mut self, cancel: CancelHandle) {
+        self.cancel = Some(cancel);
+    }
+
+    /// Returns the tracker shared by all files of the upload, or `None` if neither a progress
+    /// callback nor a cancel handle is set.
+    pub async fn tracker<'a, I>(&self, sources: I) -> Option<Tracker>
+    where
+        I: IntoIterator<Item = &'a FileSource>,
+    {
+        if self.progress.is_none() && self.cancel.is_none() {
+            return None;
+        }
+        let mut total = Some(0);
+        for source in sources {
+            total = match (total, source.len().await) {
+                (Some(total), Some(len)) => Some(total + len),
+                _ => None,
+            };
+        }
+        Some(Tracker(Arc::new(TrackerInner {
+            callback: self.progress.clone(),
+            cancel: self.cancel.clone(),
+            uploaded: AtomicU64::new(0),
+            total,
+            started: Instant::now(),
+        })))
+    }
+
+    /// Map the error of a failed request to a cancelled error if the upload was cancelled.
+    pub fn map_err(&self, err: Error) -> Error {
+        match self.cancel {
+            Some(ref cancel) if cancel.is_cancelled() => error::cancelled(),
+            _ => err,
+        }
+    }
+}
+
+/// Shared progress of the files of an upload.
+#[derive(Clone)]
+pub struct Tracker(Arc<TrackerInner>);
+
+struct TrackerInner {
+    callback: Option<ProgressFn>,
+    cancel: Option<CancelHandle>,
+    uploaded: AtomicU64,
+    total: Option<u64>,
+    started: Instant,
+}
+
+impl Tracker {
+    fn report(&self, len: u64) {
+        let uploaded = self.0.uploaded.fetch_add(len, Ordering::SeqCst) + len;
+        if let Some(ref callback) = self.0.callback {
+            let elapsed = self.0.started.elapsed().as_secs_f64();
+            (callback.0)(&UploadProgress {
+                uploaded,
+                total: self.0.total,
+                speed: if elapsed > 0.0 {
+                    uploaded as f64 / elapsed
+                } else {
+                    0.0
+                },
+            });
+        }
+    }
+}
+
+pin_project! {
+    /// Stream of the bytes of an uploaded file that reports the progress and fails once the
+    /// upload is cancelled.
+    struct Tracked<S> {
+        #[pin]
+        inner: S,
+        tracker: Tracker,
+        done: bool,
+    }
+}
+
+impl<S> Tracked<S> {
+    fn new(inner: S, tracker: Tracker) -> Tracked<S> {
+        Tracked {
+            inner,
+            tracker,
+            done: false,
+        }
+    }
+}
+
+impl<S> Stream for Tracked<S>
+where
+    S: Stream<Item = io::Result<Bytes>>,
+{
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+        if let Some(ref cancel) = this.tracker.0.cancel {
+            if cancel.poll_cancelled(cx) {
+                *this.done = true;
+                let err = io::Error::new(io::ErrorKind::Interrupted, "upload cancelled");
+                return Poll::Ready(Some(Err(err)));
+            }
+        }
+        let item = futures_core::ready!(this.inner.poll_next(cx));
+        match item {
+            Some(Ok(ref bytes)) => this.tracker.report(bytes.len() as u64),
+            Some(Err(_)) | None => *this.done = true,
+        }
+        Poll::Ready(item)
+    }
+}
+
+pin_project! {
+    /// Stream of the bytes of an uploaded file that sends the MD5 hash of the bytes once the
+    /// stream has ended.
+    struct Hashed<S> {
+        #[pin]
+        inner: S,
+        context: md5::Context,
+        tx: Option<oneshot::Sender<String>>,
+    }
+}
+
+impl<S> Hashed<S> {
+    fn new(inner: S, tx: oneshot::Sender<String>) -> Hashed<S> {
+        Hashed {
+            inner,
+            context: md5::Context::new(),
+            tx: Some(tx),
+        }
+    }
+}
+
+impl<S> Stream for Hashed<S>
+where
+    S: Stream<Item = io::Result<Bytes>>,
+{
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = futures_core::ready!(this.inner.poll_next(cx));
+        match item {
+            Some(Ok(ref bytes)) => this.context.consume(bytes),
+            Some(Err(_)) => *this.tx = None,
+            None => {
+                if let Some(tx) = this.tx.take() {
+                    let context = std::mem::replace(this.context, md5::Context::new());
+                    let _ = tx.send(format!("{:x}", context.compute()));
+                }
+            }
+        }
+        Poll::Ready(item)
     }
 }
@@ -0,0 +1,904 @@
+//! Publishing mods from a manifest file.
+//!
+//! A [`Manifest`] describes a mod: its name, summary, tags, metadata, dependencies, media and
+//! modfile. The [`Publisher`] compares the manifest with the live mod and applies the changes
+//! needed to make the mod match the manifest. The mod is created if it doesn't exist. Publishing
+//! the same manifest again makes no changes.
+//!
+//! Only the parts of the mod that are present in the manifest are changed, e.g. the tags of
+//! the mod are left alone if the manifest has no `tags`.
+//!
+//! # Manifest
+//!
+//! Manifests are TOML or JSON files. Paths are relative to the directory of the manifest.
+//!
+//! ```toml
+//! game_id = 5
+//! # The mod is found by its `mod_id` or by its `name_id` in the mods of the current user.
+//! name_id = "my-mod"
+//! name = "My Mod"
+//! summary = "A short summary of the mod."
+//! description = "<p>The description of the mod.</p>"
+//! homepage_url = "https://example.com/my-mod"
+//! visible = true
+//! logo = "logo.png"
+//! tags = ["Maps", "Weapons"]
+//! dependencies = [1024]
+//!
+//! [metadata]
+//! difficulty = "hard"
+//! modes = ["coop", "versus"]
+//!
+//! [media]
+//! images = ["screenshots/1.png", "screenshots/2.png"]
+//! youtube = ["https://www.youtube.com/watch?v=dQw4w9WgXcQ"]
+//!
+//! [modfile]
+//! # A zip archive or a directory, which is packaged with the `include` and `exclude` patterns.
+//! path = "build"
+//! exclude = ["*.bak"]
+//! version = "1.2.0"
+//! changelog = "Fixed the spawn points."
+//! ```
+//!
+//! A new modfile is uploaded if its MD5 hash differs from the hash of the current modfile of the
+//! mod. A modfile directory isn't packaged in [dry-run](Publisher::dry_run) mode, so its upload
+//! is always planned.
+//!
+//! The logo and the images are compared by their filenames only because the API doesn't expose
+//! a hash of the uploaded images. A modified image must be renamed to be uploaded again.
+//!
+//! # Example
+//! ```no_run
+//! use modio::publish::{Manifest, Publisher};
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! #     let modio = modio::Modio::new(modio::Credentials::with_token("api-key", "token"))?;
+//!
+//! let manifest = Manifest::from_file("my-mod/modio.toml").await?;
+//!
+//! // Print the planned changes without applying them.
+//! let plan = Publisher::new(modio.clone(), manifest.clone())
+//!     .dry_run(true)
+//!     .publish()
+//!     .await?;
+//! println!("{}", plan);
+//!
+//! let plan = Publisher::new(modio, manifest).publish().await?;
+//! println!("published mod {:?}", plan.mod_id());
+//! #     Ok(())
+//! # }
+//! ```
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Deserializer};
+use tokio::fs;
+use tokio::io::AsyncReadExt;
+use tracing::debug;
+use url::Url;
+
+use crate::error;
+use crate::files::{AddFileOptions, Package, PackagedMod};
+use crate::filter::prelude::*;
+use crate::metadata::MetadataMap;
+use crate::mods::filters::GameId;
+use crate::mods::{
+    AddMediaOptions, AddModOptions, DeleteMediaOptions, EditDependenciesOptions, EditModOptions,
+    EditTagsOptions, Mod, Visibility,
+};
+use crate::{Modio, Result};
+
+/// The description of a mod and its modfile.
+///
+/// See the [module documentation](self) for the format of the manifest.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct Manifest {
+    pub game_id: u32,
+    pub mod_id: Option<u32>,
+    pub name_id: Option<String>,
+    pub name: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub homepage_url: Option<Url>,
+    pub visible: Option<bool>,
+    pub metadata_blob: Option<String>,
+    pub logo: Option<PathBuf>,
+    pub tags: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_metadata")]
+    pub metadata: Option<BTreeMap<String, Vec<String>>>,
+    pub dependencies: Option<Vec<u32>>,
+    pub media: Option<MediaManifest>,
+    pub modfile: Option<ModfileManifest>,
+}
+
+/// The media of a mod in the [`Manifest`].
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct MediaManifest {
+    pub images: Option<Vec<PathBuf>>,
+    pub youtube: Option<Vec<String>>,
+    pub sketchfab: Option<Vec<String>>,
+}
+
+/// The modfile of a mod in the [`Manifest`].
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct ModfileManifest {
+    /// Path of a zip archive or a directory, which is packaged with [`Package`].
+    pub path: PathBuf,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    pub version: Option<String>,
+    pub changelog: Option<String>,
+    pub active: Option<bool>,
+    pub metadata_blob: Option<String>,
+}
+
+impl Manifest {
+    /// Read the manifest from a `.toml` or `.json` file.
+    ///
+    /// Paths in the manifest are resolved relative to the directory of the file.
+    pub async fn from_file<P: AsRef<Path>>(path: P) -> Result<Manifest> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).await.map_err(error::decode)?;
+        let manifest = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Manifest::from_toml(&content)?,
+            Some("json") => Manifest::from_json(&content)?,
+            _ => {
+                let path = path.to_path_buf();
+                return Err(error::publish(Error::UnknownFormat { path }));
+            }
+        };
+        match path.parent() {
+            Some(base) => Ok(manifest.resolve(base)),
+            None => Ok(manifest),
+        }
+    }
+
+    /// Parse a TOML manifest. Paths are relative to the current directory.
+    pub fn from_toml(s: &str) -> Result<Manifest> {
+        toml::from_str(s).map_err(|e| error::publish(Error::InvalidManifest(e.to_string())))
+    }
+
+    /// Parse a JSON manifest. Paths are relative to the current directory.
+    pub fn from_json(s: &str) -> Result<Manifest> {
+        serde_json::from_str(s).map_err(|e| error::publish(Error::InvalidManifest(e.to_string())))
+    }
+
+    fn resolve(mut self, base: &Path) -> Manifest {
+        if let Some(logo) = self.logo.as_mut() {
+            *logo = base.join(&*logo);
+        }
+        if let Some(images) = self.media.as_mut().and_then(|m| m.images.as_mut()) {
+            for image in images {
+                *image = base.join(&*image);
+            }
+        }
+        if let Some(modfile) = self.modfile.as_mut() {
+            modfile.path = base.join(&modfile.path);
+        }
+        self
+    }
+}
+
+/// A change of a mod planned by the [`Publisher`].
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Change {
+    /// Create the mod.
+    CreateMod {
+        name: String,
+    },
+    /// Edit the fields of the mod.
+    EditMod {
+        fields: Vec<Field>,
+    },
+    /// Replace the logo of the mod, which has a different filename.
+    ReplaceLogo(PathBuf),
+    AddImages(Vec<PathBuf>),
+    /// Delete the images with the filenames.
+    DeleteImages(Vec<String>),
+    AddYoutube(Vec<String>),
+    DeleteYoutube(Vec<String>),
+    AddSketchfab(Vec<String>),
+    DeleteSketchfab(Vec<String>),
+    AddTags(Vec<String>),
+    DeleteTags(Vec<String>),
+    AddMetadata(BTreeMap<String, Vec<String>>),
+    DeleteMetadata(BTreeMap<String, Vec<String>>),
+    AddDependencies(Vec<u32>),
+    DeleteDependencies(Vec<u32>),
+    /// Upload a new modfile with the MD5 hash.
+    ///
+    /// The hash is `None` for a directory in [dry-run](Publisher::dry_run) mode.
+    UploadModfile {
+        path: PathBuf,
+        version: Option<String>,
+        md5: Option<String>,
+    },
+}
+
+/// A field of the mod changed by [`Change::EditMod`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Field {
+    Name,
+    NameId,
+    Summary,
+    Description,
+    HomepageUrl,
+    Visible,
+    MetadataBlob,
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(match self {
+            Field::Name => "name",
+            Field::NameId => "name_id",
+            Field::Summary => "summary",
+            Field::Description => "description",
+            Field::HomepageUrl => "homepage_url",
+            Field::Visible => "visible",
+            Field::MetadataBlob => "metadata_blob",
+        })
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::CreateMod { name } => write!(fmt, "create mod '{}'", name),
+            Change::EditMod { fields } => write!(fmt, "edit mod: {}", join(fields)),
+            Change::ReplaceLogo(path) => write!(fmt, "replace logo with '{}'", path.display()),
+            Change::AddImages(paths) => {
+                let paths = paths.iter().map(|p| p.display().to_string());
+                write!(fmt, "add images: {}", join(paths))
+            }
+            Change::DeleteImages(names) => write!(fmt, "delete images: {}", join(names)),
+            Change::AddYoutube(urls) => write!(fmt, "add youtube links: {}", join(urls)),
+            Change::DeleteYoutube(urls) => write!(fmt, "delete youtube links: {}", join(urls)),
+            Change::AddSketchfab(urls) => write!(fmt, "add sketchfab links: {}", join(urls)),
+            Change::DeleteSketchfab(urls) => {
+                write!(fmt, "delete sketchfab links: {}", join(urls))
+            }
+            Change::AddTags(tags) => write!(fmt, "add tags: {}", join(tags)),
+            Change::DeleteTags(tags) => write!(fmt, "delete tags: {}", join(tags)),
+            Change::AddMetadata(metadata) => write!(fmt, "add metadata: {}", kvp(metadata)),
+            Change::DeleteMetadata(metadata) => {
+                write!(fmt, "delete metadata: {}", kvp(metadata))
+            }
+            Change::AddDependencies(ids) => write!(fmt, "add dependencies: {}", join(ids)),
+            Change::DeleteDependencies(ids) => {
+                write!(fmt, "delete dependencies: {}", join(ids))
+            }
+            Change::UploadModfile { path, version, md5 } => {
+                write!(fmt, "upload modfile '{}'", path.display())?;
+                if let Some(version) = version {
+                    write!(fmt, " version {}", version)?;
+                }
+                match md5 {
+                    Some(md5) => write!(fmt, " (md5: {})", md5),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+fn join<I>(items: I) -> String
+where
+    I: IntoIterator,
+    I::Item: ToString,
+{
+    items
+        .into_iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn kvp(metadata: &BTreeMap<String, Vec<String>>) -> String {
+    let kvp = metadata
+        .iter()
+        .flat_map(|(k, values)| values.iter().map(move |v| format!("{}={}", k, v)));
+    join(kvp)
+}
+
+/// The changes of a mod planned or applied by the [`Publisher`].
+#[derive(Debug)]
+pub struct Plan {
+    mod_id: Option<u32>,
+    changes: Vec<Change>,
+}
+
+impl Plan {
+    /// Returns the id of the mod, which is `None` if the mod doesn't exist yet.
+    pub fn mod_id(&self) -> Option<u32> {
+        self.mod_id
+    }
+
+    /// Returns the changes in the order they are applied.
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// Returns true if the mod already matches the manifest.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() {
+            return fmt.write_str("no changes");
+        }
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                fmt.write_str("\n")?;
+            }
+            write!(fmt, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Publishes a mod described by a [`Manifest`]. [required: token]
+pub struct Publisher {
+    modio: Modio,
+    manifest: Manifest,
+    dry_run: bool,
+}
+
+impl Publisher {
+    pub fn new(modio: Modio, manifest: Manifest) -> Publisher {
+        Publisher {
+            modio,
+            manifest,
+            dry_run: false,
+        }
+    }
+
+    /// Only plan the changes without applying them.
+    #[must_use]
+    pub fn dry_run(self, enabled: bool) -> Self {
+        Self {
+            dry_run: enabled,
+            ..self
+        }
+    }
+
+    /// Compare the manifest with the live mod and apply the changes.
+    ///
+    /// Returns the planned changes, which are not applied in [dry-run](Publisher::dry_run)
+    /// mode.
+    pub async fn publish(self) -> Result<Plan> {
+        let (mut plan, mut package) = self.plan().await?;
+        if self.dry_run {
+            return Ok(plan);
+        }
+        for change in &plan.changes {
+            debug!("publishing {}: {}", self.manifest.name, change);
+            match (change, plan.mod_id) {
+                (Change::CreateMod { .. }, _) => plan.mod_id = Some(self.create_mod().await?),
+                (_, Some(mod_id)) => self.apply(mod_id, change, &mut package).await?,
+                // Changes of a new mod are planned after `Change::CreateMod`.
+                (_, None) => unreachable!("publishing changes of a mod that doesn't exist"),
+            }
+        }
+        Ok(plan)
+    }
+
+    async fn find_mod(&self) -> Result<Option<Mod>> {
+        let game_id = self.manifest.game_id;
+        if let Some(mod_id) = self.manifest.mod_id {
+            return self.modio.mod_(game_id, mod_id).get().await.map(Some);
+        }
+        match self.manifest.name_id {
+            Some(ref name_id) => {
+                let filter = GameId::eq(game_id).and(NameId::eq(name_id));
+                self.modio.user().mods(filter).first().await
+            }
+            None => Err(error::publish(Error::MissingModId)),
+        }
+    }
+
+    async fn plan(&self) -> Result<(Plan, Option<PackagedMod>)> {
+        let manifest = &self.manifest;
+        let live = self.find_mod().await?;
+        let mut changes = Vec::new();
+
+        match live {
+            Some(ref live) => {
+                let fields = self.changed_fields(live);
+                if !fields.is_empty() {
+                    changes.push(Change::EditMod { fields });
+                }
+                if let Some(ref logo) = manifest.logo {
+                    if file_name(logo) != live.logo.filename {
+                        changes.push(Change::ReplaceLogo(logo.clone()));
+                    }
+                }
+            }
+            None => {
+                if manifest.logo.is_none() {
+                    return Err(error::publish(Error::MissingLogo));
+                }
+                changes.push(Change::CreateMod {
+                    name: manifest.name.clone(),
+                });
+            }
+        }
+
+        if let Some(ref media) = manifest.media {
+            let (images, youtube, sketchfab): (Vec<String>, _, _) = match live {
+                Some(ref live) => (
+                    live.media
+                        .images
+                        .iter()
+                        .map(|i| i.filename.clone())
+                        .collect(),
+                    live.media.youtube.clone(),
+                    live.media.sketchfab.clone(),
+                ),
+                None => Default::default(),
+            };
+            if let Some(ref wanted) = media.images {
+                let names = wanted.iter().map(|p| file_name(p)).collect::<Vec<_>>();
+                let added = wanted
+                    .iter()
+                    .filter(|p| !images.contains(&file_name(p)))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let deleted = difference(&images, &names);
+                if !deleted.is_empty() {
+                    changes.push(Change::DeleteImages(deleted));
+                }
+                if !added.is_empty() {
+                    changes.push(Change::AddImages(added));
+                }
+            }
+            if let Some(ref wanted) = media.youtube {
+                let (added, deleted) = (difference(wanted, &youtube), difference(&youtube, wanted));
+                if !deleted.is_empty() {
+                    changes.push(Change::DeleteYoutube(deleted));
+                }
+                if !added.is_empty() {
+                    changes.push(Change::AddYoutube(added));
+                }
+            }
+            if let Some(ref wanted) = media.sketchfab {
+                let (added, deleted) = (
+                    difference(wanted, &sketchfab),
+                    difference(&sketchfab, wanted),
+                );
+                if !deleted.is_empty() {
+                    changes.push(Change::DeleteSketchfab(deleted));
+                }
+                if !added.is_empty() {
+                    changes.push(Change::AddSketchfab(added));
+                }
+            }
+        }
+
+        if let Some(ref wanted) = manifest.tags {
+            let tags = match live {
+                Some(ref live) => live.tags.iter().map(|t| t.name.clone()).collect(),
+                None => Vec::new(),
+            };
+            let (added, deleted) = (difference(wanted, &tags), difference(&tags, wanted));
+            if !deleted.is_empty() {
+                changes.push(Change::DeleteTags(deleted));
+            }
+            if !added.is_empty() {
+                changes.push(Change::AddTags(added));
+            }
+        }
+
+        if let Some(ref wanted) = manifest.metadata {
+            let metadata = match live {
+                Some(ref live) => live
+                    .metadata
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+                None => BTreeMap::new(),
+            };
+            let deleted = metadata_difference(&metadata, wanted);
+            if !deleted.is_empty() {
+                changes.push(Change::DeleteMetadata(deleted));
+            }
+            let added = metadata_difference(wanted, &metadata);
+            if !added.is_empty() {
+                changes.push(Change::AddMetadata(added));
+            }
+        }
+
+        if let Some(ref wanted) = manifest.dependencies {
+            let dependencies = match live {
+                Some(ref live) => self
+                    .modio
+                    .mod_(manifest.game_id, live.id)
+                    .dependencies()
+                    .list()
+                    .await?
+                    .into_iter()
+                    .map(|d| d.mod_id)
+                    .collect(),
+                None => Vec::new(),
+            };
+            let deleted = difference(&dependencies, wanted);
+            if !deleted.is_empty() {
+                changes.push(Change::DeleteDependencies(deleted));
+            }
+            let added = difference(wanted, &dependencies);
+            if !added.is_empty() {
+                changes.push(Change::AddDependencies(added));
+            }
+        }
+
+        let mut package = None;
+        if let Some(ref modfile) = manifest.modfile {
+            let is_dir = fs::metadata(&modfile.path)
+                .await
+                .map_err(error::decode)?
+                .is_dir();
+            let md5 = if is_dir && self.dry_run {
+                // Don't package and compress the whole directory just to plan the changes.
+                None
+            } else if is_dir {
+                let mut builder = Package::new(&modfile.path);
+                for pattern in &modfile.include {
+                    builder = builder.include(pattern.clone());
+                }
+                for pattern in &modfile.exclude {
+                    builder = builder.exclude(pattern.clone());
+                }
+                let built = builder.build().await?;
+                let md5 = built.md5().to_string();
+                package = Some(built);
+                Some(md5)
+            } else {
+                Some(file_md5(&modfile.path).await?)
+            };
+            let current = live
+                .as_ref()
+                .and_then(|m| m.modfile.as_ref())
+                .map(|f| f.filehash.md5.as_str());
+            if md5.is_none() || current != md5.as_deref() {
+                changes.push(Change::UploadModfile {
+                    path: modfile.path.clone(),
+                    version: modfile.version.clone(),
+                    md5,
+                });
+            }
+        }
+
+        let plan = Plan {
+            mod_id: live.map(|m| m.id),
+            changes,
+        };
+        Ok((plan, package))
+    }
+
+    fn changed_fields(&self, live: &Mod) -> Vec<Field> {
+        let manifest = &self.manifest;
+        let mut fields = Vec::new();
+        if manifest.name != live.name {
+            fields.push(Field::Name);
+        }
+        if matches!(manifest.name_id, Some(ref name_id) if *name_id != live.name_id) {
+            fields.push(Field::NameId);
+        }
+        if manifest.summary != live.summary {
+            fields.push(Field::Summary);
+        }
+        if manifest.description.is_some() && manifest.description != live.description {
+            fields.push(Field::Description);
+        }
+        if manifest.homepage_url.is_some() && manifest.homepage_url != live.homepage_url {
+            fields.push(Field::HomepageUrl);
+        }
+        if let Some(visible) = manifest.visible {
+            if visible != matches!(live.visible, Visibility::Public) {
+                fields.push(Field::Visible);
+            }
+        }
+        if manifest.metadata_blob.is_some() && manifest.metadata_blob != live.metadata_blob {
+            fields.push(Field::MetadataBlob);
+        }
+        fields
+    }
+
+    /// Apply the change to the existing mod.
+    async fn apply(
+        &self,
+        mod_id: u32,
+        change: &Change,
+        package: &mut Option<PackagedMod>,
+    ) -> Result<()> {
+        let manifest = &self.manifest;
+        let mod_ = self.modio.mod_(manifest.game_id, mod_id);
+
+        match change {
+            Change::CreateMod { .. } => {}
+            Change::EditMod { fields } => {
+                let mut options = EditModOptions::default();
+                // Only fields that are present in the manifest are planned as changed.
+                for field in fields {
+                    options = match field {
+                        Field::Name => options.name(manifest.name.clone()),
+                        Field::NameId => {
+                            options.name_id(manifest.name_id.clone().unwrap_or_default())
+                        }
+                        Field::Summary => options.summary(manifest.summary.clone()),
+                        Field::Description => {
+                            options.description(manifest.description.clone().unwrap_or_default())
+                        }
+                        Field::HomepageUrl => match manifest.homepage_url {
+                            Some(ref url) => options.homepage_url(url.clone()),
+                            None => options,
+                        },
+                        Field::Visible => options.visible(manifest.visible.unwrap_or(true)),
+                        Field::MetadataBlob => options
+                            .metadata_blob(manifest.metadata_blob.clone().unwrap_or_default()),
+                    };
+                }
+                mod_.edit(options).await?;
+            }
+            Change::ReplaceLogo(path) => {
+                mod_.add_media(AddMediaOptions::default().logo(path))
+                    .await?;
+            }
+            Change::AddImages(paths) => {
+                mod_.add_media(AddMediaOptions::default().images(paths))
+                    .await?;
+            }
+            Change::DeleteImages(names) => {
+                mod_.delete_media(DeleteMediaOptions::default().images(names))
+                    .await?;
+            }
+            Change::AddYoutube(urls) => {
+                mod_.add_media(AddMediaOptions::default().youtube(urls))
+                    .await?;
+            }
+            Change::DeleteYoutube(urls) => {
+                mod_.delete_media(DeleteMediaOptions::default().youtube(urls))
+                    .await?;
+            }
+            Change::AddSketchfab(urls) => {
+                mod_.add_media(AddMediaOptions::default().sketchfab(urls))
+                    .await?;
+            }
+            Change::DeleteSketchfab(urls) => {
+                mod_.delete_media(DeleteMediaOptions::default().sketchfab(urls))
+                    .await?;
+            }
+            Change::AddTags(tags) => {
+                mod_.tags().add(EditTagsOptions::new(tags)).await?;
+            }
+            Change::DeleteTags(tags) => {
+                mod_.tags().delete(EditTagsOptions::new(tags)).await?;
+            }
+            Change::AddMetadata(metadata) => {
+                mod_.metadata().add(metadata_map(metadata)).await?;
+            }
+            Change::DeleteMetadata(metadata) => {
+                mod_.metadata().delete(metadata_map(metadata)).await?;
+            }
+            Change::AddDependencies(ids) => {
+                let options = EditDependenciesOptions::new(ids);
+                mod_.dependencies().add(options).await?;
+            }
+            Change::DeleteDependencies(ids) => {
+                let options = EditDependenciesOptions::new(ids);
+                mod_.dependencies().delete(options).await?;
+            }
+            Change::UploadModfile { path, .. } => {
+                let mut options = match package.take() {
+                    Some(package) => AddFileOptions::from(package),
+                    None => AddFileOptions::with_file(path),
+                }
+                .validate(true);
+                if let Some(ref modfile) = manifest.modfile {
+                    if let Some(ref version) = modfile.version {
+                        options = options.version(version.clone());
+                    }
+                    if let Some(ref changelog) = modfile.changelog {
+                        options = options.changelog(changelog.clone());
+                    }
+                    if let Some(active) = modfile.active {
+                        options = options.active(active);
+                    }
+                    if let Some(ref metadata_blob) = modfile.metadata_blob {
+                        options = options.metadata_blob(metadata_blob.clone());
+                    }
+                }
+                mod_.files().add(options).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn create_mod(&self) -> Result<u32> {
+        let manifest = &self.manifest;
+        let logo = manifest
+            .logo
+            .as_ref()
+            .ok_or_else(|| error::publish(Error::MissingLogo))?;
+        let mut options = AddModOptions::new(manifest.name.clone(), logo, manifest.summary.clone());
+        if let Some(ref name_id) = manifest.name_id {
+            options = options.name_id(name_id.clone());
+        }
+        if let Some(ref description) = manifest.description {
+            options = options.description(description.clone());
+        }
+        if let Some(ref url) = manifest.homepage_url {
+            options = options.homepage_url(url.clone());
+        }
+        if let Some(visible) = manifest.visible {
+            options = options.visible(visible);
+        }
+        if let Some(ref metadata_blob) = manifest.metadata_blob {
+            options = options.metadata_blob(metadata_blob.clone());
+        }
+        let created = self
+            .modio
+            .game(manifest.game_id)
+            .mods()
+            .add(options)
+            .await?;
+        Ok(created.id)
+    }
+}
+
+/// Returns the items of `a` that are not in `b`.
+fn difference<T: Clone + PartialEq>(a: &[T], b: &[T]) -> Vec<T> {
+    let mut diff = Vec::new();
+    for item in a {
+        if !b.contains(item) && !diff.contains(item) {
+            diff.push(item.clone());
+        }
+    }
+    diff
+}
+
+/// Returns the key value pairs of `a` that are not in `b`.
+fn metadata_difference(
+    a: &BTreeMap<String, Vec<String>>,
+    b: &BTreeMap<String, Vec<String>>,
+) -> BTreeMap<String, Vec<String>> {
+    let empty = Vec::new();
+    a.iter()
+        .filter_map(|(key, values)| {
+            let diff = difference(values, b.get(key).unwrap_or(&empty));
+            if diff.is_empty() {
+                None
+            } else {
+                Some((key.clone(), diff))
+            }
+        })
+        .collect()
+}
+
+fn metadata_map(metadata: &BTreeMap<String, Vec<String>>) -> MetadataMap {
+    let mut map = MetadataMap::with_capacity(metadata.len());
+    for (key, values) in metadata {
+        map.insert(key.clone(), values.clone());
+    }
+    map
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .map_or_else(String::new, ToString::to_string)
+}
+
+async fn file_md5(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path).await.map_err(error::decode)?;
+    let mut context = md5::Context::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).await.map_err(error::decode)?;
+        if n == 0 {
+            break;
+        }
+        context.consume(&buf[..n]);
+    }
+    Ok(format!("{:x}", context.compute()))
+}
+
+/// Deserialize metadata values that are a single string or a list of strings.
+fn deserialize_metadata<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<BTreeMap<String, Vec<String>>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Values {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    let map = Option::<BTreeMap<String, Values>>::deserialize(deserializer)?;
+    Ok(map.map(|map| {
+        map.into_iter()
+            .map(|(k, v)| match v {
+                Values::One(v) => (k, vec![v]),
+                Values::Many(v) => (k, v),
+            })
+            .collect()
+    }))
+}
+
+/// The Errors that may occur when a mod is published with the [`Publisher`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The manifest is not valid TOML or JSON.
+    InvalidManifest(String),
+    /// The manifest file is neither a `.toml` nor a `.json` file.
+    UnknownFormat { path: PathBuf },
+    /// The manifest has neither a `mod_id` nor a `name_id` to find the mod.
+    MissingModId,
+    /// The mod doesn't exist and the manifest has no logo to create it.
+    MissingLogo,
+}
+
+impl StdError for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidManifest(e) => write!(fmt, "invalid manifest: {}", e),
+            Error::UnknownFormat { path } => write!(
+                fmt,
+                "unknown manifest format of '{}', expected a .toml or .json file.",
+                path.display()
+            ),
+            Error::MissingModId => {
+                fmt.write_str("manifest requires a `mod_id` or `name_id` to find the mod.")
+            }
+            Error::MissingLogo => fmt.write_str("manifest requires a `logo` to create the mod."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::metadata_difference;
+
+    #[test]
+    fn metadata_changes() {
+        let map = |kvp: &[(&str, &[&str])]| {
+            kvp.iter()
+                .map(|(k, v)| (k.to_string(), v.iter().map(|v| v.to_string()).collect()))
+                .collect::<BTreeMap<String, Vec<String>>>()
+        };
+        let live = map(&[("modes", &["coop", "solo"]), ("difficulty", &["easy"])]);
+        let wanted = map(&[("modes", &["coop", "versus"])]);
+
+        assert_eq!(
+            metadata_difference(&live, &wanted),
+            map(&[("modes", &["solo"]), ("difficulty", &["easy"])])
+        );
+        assert_eq!(
+            metadata_difference(&wanted, &live),
+            map(&[("modes", &["versus"])])
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use http::HeaderMap;
+use tokio::time::{sleep_until, Instant};
+use tracing::debug;
+
+use crate::request::headers;
+
+/// The rate limits of mod.io are defined per minute.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Client-side token bucket fed by the `X-RateLimit-*` headers of every response.
+///
+/// The bucket is unlimited until a response reports a rate limit. Afterwards the bucket
+/// refills continuously at `limit` tokens per minute, and every request takes a token. If the
+/// budget drops to the configured reserve, requests are delayed until enough tokens are
+/// available again.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    reserve: u32,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    limit: Option<u32>,
+    tokens: f64,
+    updated: Instant,
+    blocked_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(reserve: u32) -> RateLimiter {
+        RateLimiter {
+            reserve,
+            state: Mutex::new(State {
+                limit: None,
+                tokens: 0.0,
+                updated: Instant::now(),
+                blocked_until: None,
+            }),
+        }
+    }
+
+    /// Creates an empty limiter with the same configuration, e.g. for new credentials.
+    pub(crate) fn fresh(&self) -> RateLimiter {
+        RateLimiter::new(self.reserve)
+    }
+
+    /// Takes a token from the bucket and waits until the request is within the budget.
+    pub(crate) async fn acquire(&self) {
+        let deadline = {
+            let mut state = self.state.lock().expect("ratelimit state poisoned");
+            let now = Instant::now();
+            let blocked = state.blocked_until.filter(|t| *t > now);
+
+            let delayed = match state.limit {
+                Some(limit) if limit > 0 => {
+                    state.refill(now, limit);
+                    state.tokens -= 1.0;
+
+                    let deficit = f64::from(self.reserve) - state.tokens;
+                    if deficit > 0.0 {
+                        let rate = f64::from(limit) / WINDOW.as_secs_f64();
+                        Some(now + Duration::from_secs_f64(deficit / rate))
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            };
+            match (blocked, delayed) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            }
+        };
+
+        if let Some(deadline) = deadline {
+            debug!(
+                "ratelimit budget exhausted: delaying request for {:?}",
+                deadline - Instant::now()
+            );
+            sleep_until(deadline).await;
+        }
+    }
+
+    /// Updates the budget from the rate limit headers of a response.
+    pub(crate) fn update(&self, headers: &HeaderMap) {
+        let (limit, remaining, retry_after) = headers::parse_ratelimit(headers);
+        if limit.is_none() && remaining.is_none() && retry_after.is_none() {
+            return;
+        }
+
+        let mut state = self.state.lock().expect("ratelimit state poisoned");
+        let now = Instant::now();
+        if let Some(limit) = limit {
+            if state.limit.is_none() {
+                state.tokens = f64::from(limit);
+                state.updated = now;
+            }
+            state.limit = Some(limit);
+        }
+        if let Some(limit) = state.limit {
+            state.refill(now, limit);
+        }
+        if let Some(remaining) = remaining {
+            // The server's view of the budget wins if it is lower than ours.
+            state.tokens = state.tokens.min(f64::from(remaining));
+        }
+        if let Some(secs) = retry_after {
+            state.blocked_until = Some(now + Duration::from_secs(secs));
+            state.tokens = state.tokens.min(0.0);
+        }
+    }
+}
+
+impl State {
+    fn refill(&mut self, now: Instant, limit: u32) {
+        let rate = f64::from(limit) / WINDOW.as_secs_f64();
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(f64::from(limit));
+        self.updated = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::{HeaderMap, HeaderValue};
+    use tokio::time::Instant;
+
+    use super::RateLimiter;
+
+    fn headers(limit: u32, remaining: u32) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit", HeaderValue::from(limit));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from(remaining));
+        headers
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unlimited_without_headers() {
+        let limiter = RateLimiter::new(0);
+        let start = Instant::now();
+        for _ in 0..100 {
+            limiter.acquire().await;
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn delay_when_budget_is_exhausted() {
+        let limiter = RateLimiter::new(0);
+        limiter.update(&headers(60, 2));
+
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        // 60 requests per minute refill one token per second.
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keep_reserve() {
+        let limiter = RateLimiter::new(5);
+        limiter.update(&headers(60, 6));
+
+        let start = Instant::now();
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn honour_retry_after() {
+        let limiter = RateLimiter::new(0);
+        let mut headers = headers(60, 0);
+        headers.insert("x-ratelimit-retryafter", HeaderValue::from(30));
+        limiter.update(&headers);
+
+        let start = Instant::now();
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(30));
+    }
+}
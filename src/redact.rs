@@ -0,0 +1,154 @@
+//! Redaction of credentials and signed urls from tracing output and errors.
+use std::fmt;
+
+use http::header::{HeaderMap, AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, SET_COOKIE};
+use serde_json::Value;
+use url::Url;
+
+use crate::transport::BoxError;
+
+const REDACTED: &str = "REDACTED";
+
+/// Fields of response bodies that contain credentials or signed urls.
+const SENSITIVE_FIELDS: &[&str] = &["access_token", "api_key", "binary_url"];
+
+fn is_sensitive_param(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    ["key", "token", "sig", "secret", "credential"]
+        .iter()
+        .any(|s| name.contains(s))
+}
+
+/// Returns a copy of the url without credentials.
+///
+/// The values of query parameters like `api_key` are replaced, as well as the path segments
+/// following the `download` segment of signed download urls.
+pub fn url(url: &Url) -> Url {
+    let mut url = url.clone();
+
+    if url.query().is_some() {
+        let pairs = url
+            .query_pairs()
+            .map(|(k, v)| {
+                let v = if is_sensitive_param(&k) {
+                    REDACTED.into()
+                } else {
+                    v
+                };
+                (k.into_owned(), v.into_owned())
+            })
+            .collect::<Vec<_>>();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+
+    let segments = url
+        .path_segments()
+        .map(|s| s.map(ToString::to_string).collect::<Vec<_>>());
+    if let Some(segments) = segments {
+        if let Some(pos) = segments.iter().position(|s| s == "download") {
+            if pos + 1 < segments.len() {
+                if let Ok(mut path) = url.path_segments_mut() {
+                    path.clear().extend(&segments[..=pos]).push(REDACTED);
+                }
+            }
+        }
+    }
+    if url.password().is_some() {
+        let _ = url.set_password(Some(REDACTED));
+    }
+    url
+}
+
+/// Redacts the url of a `reqwest::Error` which is included in its `Display` output.
+pub fn error(mut err: reqwest::Error) -> reqwest::Error {
+    if let Some(u) = err.url_mut() {
+        *u = url(u);
+    }
+    err
+}
+
+/// Redacts the url of a boxed `reqwest::Error` returned by a transport.
+pub fn boxed_error(err: BoxError) -> BoxError {
+    match err.downcast::<reqwest::Error>() {
+        Ok(err) => Box::new(error(*err)),
+        Err(err) => err,
+    }
+}
+
+/// `Debug` formatting of headers with the values of sensitive headers replaced.
+pub struct Headers<'a>(pub &'a HeaderMap);
+
+impl fmt::Debug for Headers<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sensitive = [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE, SET_COOKIE];
+        f.debug_map()
+            .entries(self.0.iter().map(|(name, value)| {
+                if sensitive.contains(name) || value.is_sensitive() {
+                    (name.as_str(), &REDACTED as &dyn fmt::Debug)
+                } else {
+                    (name.as_str(), value as &dyn fmt::Debug)
+                }
+            }))
+            .finish()
+    }
+}
+
+/// Returns the response body as text with sensitive fields of JSON objects replaced.
+pub fn body(body: &[u8]) -> String {
+    fn redact(value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (k, v) in map.iter_mut() {
+                    if SENSITIVE_FIELDS.contains(&k.as_str()) {
+                        *v = Value::String(REDACTED.to_string());
+                    } else {
+                        redact(v);
+                    }
+                }
+            }
+            Value::Array(list) => list.iter_mut().for_each(redact),
+            _ => {}
+        }
+    }
+
+    match serde_json::from_slice::<Value>(body) {
+        Ok(mut value) => {
+            redact(&mut value);
+            value.to_string()
+        }
+        Err(_) => match std::str::from_utf8(body) {
+            Ok(s) => s.to_string(),
+            Err(_) => format!("{:?}", body),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    #[test]
+    fn redact_url() {
+        let url = Url::parse("https://api.mod.io/v1/games?api_key=secret&_limit=10").unwrap();
+        assert_eq!(
+            super::url(&url).as_str(),
+            "https://api.mod.io/v1/games?api_key=REDACTED&_limit=10"
+        );
+
+        let url =
+            Url::parse("https://api.mod.io/v1/games/1/mods/2/files/3/download/c489a0354").unwrap();
+        assert_eq!(
+            super::url(&url).as_str(),
+            "https://api.mod.io/v1/games/1/mods/2/files/3/download/REDACTED"
+        );
+    }
+
+    #[test]
+    fn redact_body() {
+        let body = br#"{"access_token":"secret","date_expires":1,"data":[{"download":{"binary_url":"https://x"}}]}"#;
+        let body = super::body(body);
+        assert!(!body.contains("secret"));
+        assert!(!body.contains("https://x"));
+        assert!(body.contains("date_expires"));
+    }
+}
@@ -1,7 +1,9 @@
+use std::convert::TryFrom;
+
 use futures_util::TryFutureExt;
-use reqwest::header::{HeaderValue, CONTENT_TYPE};
+use reqwest::header::{HeaderName, HeaderValue, CONTENT_TYPE};
 use reqwest::multipart::Form;
-use reqwest::StatusCode;
+use reqwest::{Body, StatusCode};
 use serde::de::DeserializeOwned;
 use serde::ser::Serialize;
 use tracing::{debug, level_enabled, trace};
@@ -9,12 +11,15 @@ use url::Url;
 
 use crate::auth::Token;
 use crate::error::{self, Result};
+use crate::redact;
+use crate::response::ResponseMeta;
+use crate::retry::RetryPolicy;
 use crate::routing::{AuthMethod, Route};
 use crate::types::ErrorResponse;
 use crate::Modio;
 
 #[allow(dead_code)]
-mod headers {
+pub(crate) mod headers {
     const X_MODIO_ERROR_REF: &str = "x-modio-error-ref";
     const X_MODIO_REQUEST_ID: &str = "x-modio-request-id";
     const X_RATELIMIT_LIMIT: &str = "x-ratelimit-limit";
@@ -23,23 +28,37 @@ mod headers {
 
     use http::HeaderMap;
 
-    pub fn parse_headers(headers: &HeaderMap) -> (Option<u64>, Option<u64>) {
-        fn to_str<'a>(headers: &'a HeaderMap, name: &'static str) -> Option<&'a str> {
-            headers.get(name).and_then(|v| v.to_str().ok())
-        }
-        fn parse<T: std::str::FromStr>(headers: &HeaderMap, name: &'static str) -> Option<T> {
-            to_str(headers, name).and_then(|v| v.parse().ok())
-        }
+    fn to_str<'a>(headers: &'a HeaderMap, name: &'static str) -> Option<&'a str> {
+        headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    fn parse<T: std::str::FromStr>(headers: &HeaderMap, name: &'static str) -> Option<T> {
+        to_str(headers, name).and_then(|v| v.parse().ok())
+    }
 
+    pub fn parse_headers(headers: &HeaderMap) -> (Option<u64>, Option<u64>) {
         let remaining = parse(headers, X_RATELIMIT_REMAINING);
         let reset_after = parse(headers, X_RATELIMIT_RETRY_AFTER);
         (remaining, reset_after)
     }
+
+    pub fn parse_request_id(headers: &HeaderMap) -> Option<String> {
+        to_str(headers, X_MODIO_REQUEST_ID).map(ToString::to_string)
+    }
+
+    /// Returns the limit, the remaining requests and the seconds until the limit resets.
+    pub fn parse_ratelimit(headers: &HeaderMap) -> (Option<u32>, Option<u32>, Option<u64>) {
+        let limit = parse(headers, X_RATELIMIT_LIMIT);
+        let remaining = parse(headers, X_RATELIMIT_REMAINING);
+        let reset_after = parse(headers, X_RATELIMIT_RETRY_AFTER);
+        (limit, remaining, reset_after)
+    }
 }
 
 pub struct RequestBuilder {
     modio: Modio,
     request: Result<reqwest::RequestBuilder>,
+    retry: Option<RetryPolicy>,
 }
 
 impl RequestBuilder {
@@ -50,6 +69,7 @@ impl RequestBuilder {
             return Self {
                 modio,
                 request: Err(error::token_required()),
+                retry: None,
             };
         }
 
@@ -57,7 +77,11 @@ impl RequestBuilder {
         let params = [("api_key", &modio.inner.credentials.api_key)];
         let request = Url::parse_with_params(&url, &params)
             .map(|url| {
-                let mut req = modio.inner.client.request(method, url);
+                let mut req = modio
+                    .inner
+                    .client
+                    .request(method, url)
+                    .headers(modio.inner.headers.clone());
 
                 if let (AuthMethod::Token, Some(Token { value, .. })) =
                     (&auth_method, &modio.inner.credentials.token)
@@ -68,7 +92,11 @@ impl RequestBuilder {
             })
             .map_err(error::builder);
 
-        Self { modio, request }
+        Self {
+            modio,
+            request,
+            retry: None,
+        }
     }
 
     pub fn query<T: Serialize + ?Sized>(self, query: &T) -> Self {
@@ -92,11 +120,40 @@ impl RequestBuilder {
         }
     }
 
+    pub fn header<V>(self, name: HeaderName, value: V) -> Self
+    where
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        Self {
+            request: self.request.map(|r| r.header(name, value)),
+            ..self
+        }
+    }
+
+    pub fn body<B: Into<Body>>(self, body: B) -> Self {
+        Self {
+            request: self.request.map(|r| r.body(body)),
+            ..self
+        }
+    }
+
+    /// Use `policy` instead of the retry policy of the client.
+    pub fn retry(self, policy: RetryPolicy) -> Self {
+        Self {
+            retry: Some(policy),
+            ..self
+        }
+    }
+
     pub async fn send<Out>(self) -> Result<Out>
     where
         Out: DeserializeOwned + Send,
     {
-        let mut req = self.request?.build().map_err(error::builder)?;
+        let mut req = self
+            .request?
+            .build()
+            .map_err(|e| error::builder(redact::error(e)))?;
         if !req.headers().contains_key(CONTENT_TYPE) {
             req.headers_mut().insert(
                 CONTENT_TYPE,
@@ -104,48 +161,101 @@ impl RequestBuilder {
             );
         }
 
-        debug!("request: {} {}", req.method(), req.url());
-        let response = self
-            .modio
-            .inner
-            .client
-            .execute(req)
-            .map_err(error::request)
-            .await?;
+        let policy = self.retry.as_ref().unwrap_or(&self.modio.inner.retry);
+        let retryable = policy.allows_method(req.method());
+        let mut attempt = 1;
+        loop {
+            // Requests with a streaming body can't be cloned and are sent only once.
+            let next = if retryable && attempt < policy.max_attempts() {
+                req.try_clone()
+            } else {
+                None
+            };
 
-        let status = response.status();
+            if let Some(ratelimit) = &self.modio.inner.ratelimit {
+                ratelimit.acquire().await;
+            }
+            let err = match execute(&self.modio, req).await {
+                Ok(out) => return Ok(out),
+                Err(err) => err,
+            };
+            match (next, policy.delay_for(attempt, &err)) {
+                (Some(next), Some(delay)) => {
+                    debug!(
+                        "retrying request in {:?} (attempt {}): {}",
+                        delay, attempt, err
+                    );
+                    tokio::time::sleep(delay).await;
+                    req = next;
+                    attempt += 1;
+                }
+                _ => return Err(err),
+            }
+        }
+    }
+}
 
-        let (remaining, reset) = if status.is_success() {
-            (None, None)
-        } else {
-            headers::parse_headers(response.headers())
-        };
+async fn execute<Out>(modio: &Modio, req: reqwest::Request) -> Result<Out>
+where
+    Out: DeserializeOwned + Send,
+{
+    debug!("request: {} {}", req.method(), redact::url(req.url()));
+    let response = modio
+        .inner
+        .transport
+        .send(req)
+        .map_err(|e| error::request(redact::boxed_error(e)))
+        .await?;
 
-        trace!("response headers: {:?}", response.headers());
+    let status = response.status();
+    let meta = ResponseMeta::new(status, response.headers());
 
-        let body = response.bytes().map_err(error::request).await?;
+    if let Some(ratelimit) = &modio.inner.ratelimit {
+        ratelimit.update(response.headers());
+    }
+    if let Some(hook) = &modio.inner.on_response {
+        hook.call(&meta);
+    }
+    meta.record();
 
-        if level_enabled!(tracing::Level::TRACE) {
-            match std::str::from_utf8(&body) {
-                Ok(s) => trace!("status: {}, response: {}", status, s),
-                Err(_) => trace!("status: {}, response: {:?}", status, body),
-            }
-        }
+    let (remaining, reset) = if status.is_success() {
+        (None, None)
+    } else {
+        headers::parse_headers(response.headers())
+    };
 
-        if status == StatusCode::NO_CONTENT {
-            serde_json::from_str("null").map_err(error::decode)
-        } else if status.is_success() {
-            serde_json::from_slice(&body).map_err(error::decode)
-        } else {
-            match (remaining, reset) {
-                (Some(remaining), Some(reset)) if remaining == 0 => {
-                    debug!("ratelimit reached: reset in {} seconds", reset);
-                    Err(error::ratelimit(reset))
-                }
-                _ => serde_json::from_slice::<ErrorResponse>(&body)
-                    .map(|mer| Err(error::error_for_status(status, mer.error)))
-                    .map_err(error::decode)?,
+    trace!(
+        "response headers: {:?}",
+        redact::Headers(response.headers())
+    );
+
+    let request_id = meta.request_id;
+    let body = response
+        .bytes()
+        .map_err(|e| error::request(redact::error(e)).with_request_id(request_id.clone()))
+        .await?;
+
+    if level_enabled!(tracing::Level::TRACE) {
+        trace!("status: {}, response: {}", status, redact::body(&body));
+    }
+
+    let result = if status == StatusCode::NO_CONTENT {
+        serde_json::from_str("null").map_err(error::decode)
+    } else if status.is_success() {
+        serde_json::from_slice(&body).map_err(error::decode)
+    } else {
+        match (remaining, reset) {
+            (Some(0), Some(reset)) => {
+                debug!("ratelimit reached: reset in {} seconds", reset);
+                Err(error::ratelimit(reset))
             }
+            _ => match serde_json::from_slice::<ErrorResponse>(&body) {
+                Ok(mer) => Err(error::error_for_status(status, mer.error)),
+                // Gateways and proxies respond with non-JSON bodies for server errors.
+                Err(e) if status.is_server_error() => Err(error::status(status, e)),
+                Err(e) => Err(error::decode(e)),
+            },
         }
-    }
+    };
+    result.map_err(|e| e.with_request_id(request_id))
 }
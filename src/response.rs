@@ -0,0 +1,114 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use http::HeaderMap;
+use reqwest::StatusCode;
+
+use crate::error::Result;
+use crate::request::headers;
+
+tokio::task_local! {
+    static LAST_RESPONSE: RefCell<Option<ResponseMeta>>;
+}
+
+/// Metadata of a response from the mod.io API.
+///
+/// Returned next to the result of a single call by [`ResponseMeta::capture`] and passed to the
+/// callback registered with [`Builder::on_response`](crate::Builder::on_response) for every
+/// response, whether the request succeeded or not.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ResponseMeta {
+    /// The status code of the response.
+    pub status: StatusCode,
+    /// The unique id of the request, from the `X-Modio-Request-Id` header.
+    ///
+    /// Include it when contacting the mod.io support about a failed request.
+    pub request_id: Option<String>,
+    /// The number of requests allowed per minute, from the `X-RateLimit-Limit` header.
+    pub ratelimit_limit: Option<u32>,
+    /// The number of requests remaining, from the `X-RateLimit-Remaining` header.
+    pub ratelimit_remaining: Option<u32>,
+    /// The duration until the rate limit resets, from the `X-RateLimit-RetryAfter` header.
+    pub ratelimit_retry_after: Option<Duration>,
+}
+
+impl ResponseMeta {
+    pub(crate) fn new(status: StatusCode, headers: &HeaderMap) -> ResponseMeta {
+        let (limit, remaining, retry_after) = headers::parse_ratelimit(headers);
+        ResponseMeta {
+            status,
+            request_id: headers::parse_request_id(headers),
+            ratelimit_limit: limit,
+            ratelimit_remaining: remaining,
+            ratelimit_retry_after: retry_after.map(Duration::from_secs),
+        }
+    }
+
+    /// Await the API call `future` and return its result together with the metadata of the
+    /// last response received by the call.
+    ///
+    /// The metadata is `None` if the call succeeded without a request to the API. The request
+    /// id of a failed call is available with [`Error::request_id`](crate::Error::request_id).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use modio::ResponseMeta;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// #     let modio = modio::Modio::new("api-key")?;
+    ///
+    /// let (game, meta) = ResponseMeta::capture(modio.game(5).get()).await?;
+    /// if let Some(meta) = meta {
+    ///     println!(
+    ///         "{}: request-id: {:?} remaining: {:?}",
+    ///         game.name, meta.request_id, meta.ratelimit_remaining
+    ///     );
+    /// }
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub async fn capture<F, T>(future: F) -> Result<(T, Option<ResponseMeta>)>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        LAST_RESPONSE
+            .scope(RefCell::new(None), async {
+                let out = future.await?;
+                let meta = LAST_RESPONSE.with(|meta| meta.borrow_mut().take());
+                Ok((out, meta))
+            })
+            .await
+    }
+
+    /// Record the metadata for an enclosing [`ResponseMeta::capture`].
+    pub(crate) fn record(&self) {
+        let _ = LAST_RESPONSE.try_with(|meta| *meta.borrow_mut() = Some(self.clone()));
+    }
+}
+
+type Callback = dyn Fn(&ResponseMeta) + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) struct ResponseHook(Arc<Callback>);
+
+impl ResponseHook {
+    pub(crate) fn new<F>(f: F) -> ResponseHook
+    where
+        F: Fn(&ResponseMeta) + Send + Sync + 'static,
+    {
+        ResponseHook(Arc::new(f))
+    }
+
+    pub(crate) fn call(&self, meta: &ResponseMeta) {
+        (self.0)(meta);
+    }
+}
+
+impl fmt::Debug for ResponseHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ResponseHook")
+    }
+}
@@ -0,0 +1,200 @@
+//! Retry policy for failed requests.
+//!
+//! By default a failed request is returned immediately to the caller. A [`RetryPolicy`]
+//! configured with [`Builder::retry`](crate::Builder::retry) lets the client retry requests
+//! that failed with a transient error.
+//!
+//! # Example
+//! ```no_run
+//! use std::time::Duration;
+//! use modio::retry::{Backoff, RetryOn, RetryPolicy};
+//! # fn main() -> modio::Result<()> {
+//!
+//! let policy = RetryPolicy::new(5)
+//!     .backoff(Backoff::exponential(
+//!         Duration::from_millis(500),
+//!         Duration::from_secs(30),
+//!     ))
+//!     .retry_on(RetryOn::REQUEST | RetryOn::RATELIMIT);
+//!
+//! let modio = modio::Modio::builder("user-or-game-api-key")
+//!     .retry(policy)
+//!     .build()?;
+//! #     Ok(())
+//! # }
+//! ```
+use std::time::Duration;
+
+use reqwest::Method;
+
+use crate::error::{Error, Kind};
+
+bitflags::bitflags! {
+    /// The kinds of errors that are retried by a [`RetryPolicy`].
+    pub struct RetryOn: u8 {
+        /// Errors of the underlying HTTP request, e.g. connection resets or timeouts.
+        const REQUEST = 0b001;
+        /// Responses with a `5xx` status code.
+        const SERVER_ERROR = 0b010;
+        /// Exhausted rate limits. The retry waits for the duration of the
+        /// `X-RateLimit-RetryAfter` header instead of the backoff.
+        const RATELIMIT = 0b100;
+    }
+}
+
+impl Default for RetryOn {
+    fn default() -> RetryOn {
+        RetryOn::all()
+    }
+}
+
+/// Defines the delay between two attempts of a request.
+#[derive(Clone, Copy, Debug)]
+pub enum Backoff {
+    /// Wait the same duration before every retry.
+    Fixed(Duration),
+    /// Double the delay after every attempt, starting with `base` and capped at `max`.
+    Exponential { base: Duration, max: Duration },
+}
+
+impl Backoff {
+    /// Wait the same duration before every retry.
+    pub fn fixed(delay: Duration) -> Backoff {
+        Backoff::Fixed(delay)
+    }
+
+    /// Double the delay after every attempt, starting with `base` and capped at `max`.
+    pub fn exponential(base: Duration, max: Duration) -> Backoff {
+        Backoff::Exponential { base, max }
+    }
+
+    /// Returns the delay before the next attempt. `attempt` starts at `1`.
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        match *self {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { base, max } => {
+                let factor = 1u32
+                    .checked_shl(attempt.saturating_sub(1))
+                    .unwrap_or(u32::MAX);
+                base.checked_mul(factor).map_or(max, |delay| delay.min(max))
+            }
+        }
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Backoff {
+        Backoff::Exponential {
+            base: Duration::from_millis(500),
+            max: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Configuration of the automatic retries for failed requests.
+///
+/// Requests with non-idempotent methods like `POST` are never retried unless
+/// enabled with [`RetryPolicy::non_idempotent`]. Requests with a streaming body, e.g.
+/// file uploads, can't be retried at all.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Backoff,
+    retry_on: RetryOn,
+    non_idempotent: bool,
+}
+
+impl RetryPolicy {
+    /// Creates a policy that sends a request at most `max_attempts` times.
+    ///
+    /// By default all [`RetryOn`] errors are retried with an exponential backoff starting at
+    /// 500ms and capped at 30s.
+    pub fn new(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            backoff: Backoff::default(),
+            retry_on: RetryOn::default(),
+            non_idempotent: false,
+        }
+    }
+
+    /// Creates a policy that never retries a failed request.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy::new(1)
+    }
+
+    /// Set the backoff between two attempts.
+    #[must_use]
+    pub fn backoff(self, backoff: Backoff) -> RetryPolicy {
+        RetryPolicy { backoff, ..self }
+    }
+
+    /// Set the kinds of errors that are retried.
+    #[must_use]
+    pub fn retry_on(self, retry_on: RetryOn) -> RetryPolicy {
+        RetryPolicy { retry_on, ..self }
+    }
+
+    /// Allow requests with non-idempotent methods like `POST` to be retried.
+    #[must_use]
+    pub fn non_idempotent(self, enabled: bool) -> RetryPolicy {
+        RetryPolicy {
+            non_idempotent: enabled,
+            ..self
+        }
+    }
+
+    pub(crate) fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    pub(crate) fn allows_method(&self, method: &Method) -> bool {
+        let idempotent = matches!(
+            *method,
+            Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+        );
+        idempotent || self.non_idempotent
+    }
+
+    /// Returns the delay before the next attempt if the error is retryable.
+    pub(crate) fn delay_for(&self, attempt: u32, err: &Error) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        match err.kind() {
+            Kind::Request if self.retry_on.contains(RetryOn::REQUEST) => {
+                Some(self.backoff.delay(attempt))
+            }
+            Kind::Status(status)
+                if status.is_server_error() && self.retry_on.contains(RetryOn::SERVER_ERROR) =>
+            {
+                Some(self.backoff.delay(attempt))
+            }
+            Kind::RateLimit { reset } if self.retry_on.contains(RetryOn::RATELIMIT) => Some(*reset),
+            _ => None,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Backoff;
+
+    #[test]
+    fn exponential_backoff() {
+        let backoff = Backoff::exponential(Duration::from_secs(1), Duration::from_secs(10));
+        assert_eq!(backoff.delay(1), Duration::from_secs(1));
+        assert_eq!(backoff.delay(2), Duration::from_secs(2));
+        assert_eq!(backoff.delay(3), Duration::from_secs(4));
+        assert_eq!(backoff.delay(5), Duration::from_secs(10));
+        assert_eq!(backoff.delay(64), Duration::from_secs(10));
+    }
+}
@@ -153,6 +153,12 @@ define_routes! {
     { AddFile, POST: "/games/{}/mods/{}/files", [game_id, mod_id], Token },
     { EditFile, PUT: "/games/{}/mods/{}/files/{}", [game_id, mod_id, file_id], Token },
     { DeleteFile, DELETE: "/games/{}/mods/{}/files/{}", [game_id, mod_id, file_id], Token },
+    { CreateMultipartUploadSession, POST: "/games/{}/mods/{}/files/multipart", [game_id, mod_id], Token },
+    { GetMultipartUploadParts, GET: "/games/{}/mods/{}/files/multipart", [game_id, mod_id], Token },
+    { AddMultipartUploadPart, PUT: "/games/{}/mods/{}/files/multipart", [game_id, mod_id], Token },
+    { DeleteMultipartUploadSession, DELETE: "/games/{}/mods/{}/files/multipart", [game_id, mod_id], Token },
+    { CompleteMultipartUploadSession, POST: "/games/{}/mods/{}/files/multipart/complete", [game_id, mod_id], Token },
+    { GetMultipartUploadSessions, GET: "/games/{}/mods/{}/files/multipart/sessions", [game_id, mod_id], Token },
     { ManagePlatformStatus, POST: "/games/{}/mods/{}/files/{}/platforms", [game_id, mod_id, file_id], Token },
     { AuthorizedUser, GET: "/me", Token },
     { UserSubscriptions, GET: "/me/subscribed", Token },
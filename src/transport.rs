@@ -0,0 +1,283 @@
+//! Pluggable HTTP transport.
+//!
+//! Every request of a [`Modio`](crate::Modio) client, including file downloads, is dispatched
+//! through a [`Transport`]. By default the client uses a `reqwest::Client` configured with the
+//! [`Builder`](crate::Builder). A custom transport can be set with
+//! [`Builder::transport`](crate::Builder::transport) to use a different HTTP stack, or the
+//! [`MemoryTransport`] to run the API without network access in tests.
+//!
+//! # Example
+//! ```no_run
+//! use modio::transport::{BoxError, Request, Response};
+//! # fn main() -> modio::Result<()> {
+//!
+//! // Any `Fn(Request) -> impl Future<Output = Result<Response, BoxError>>` is a transport.
+//! let client = reqwest::Client::new();
+//! let modio = modio::Modio::builder("user-or-game-api-key")
+//!     .transport(move |req: Request| {
+//!         let client = client.clone();
+//!         async move {
+//!             println!("{} {}", req.method(), req.url().path());
+//!             client.execute(req).await.map_err(BoxError::from)
+//!         }
+//!     })
+//!     .build()?;
+//! #     Ok(())
+//! # }
+//! ```
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_util::{future, TryFutureExt, TryStreamExt};
+use http::{Method, StatusCode};
+
+pub use reqwest::{Body, Request, Response};
+
+/// A boxed error returned by a [`Transport`].
+pub type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// A boxed future returned by a [`Transport`].
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+
+/// Sends a `Request` and returns the `Response` with a streaming body.
+pub trait Transport: Send + Sync + 'static {
+    fn send(&self, request: Request) -> BoxFuture<Result<Response, BoxError>>;
+}
+
+impl Transport for reqwest::Client {
+    fn send(&self, request: Request) -> BoxFuture<Result<Response, BoxError>> {
+        Box::pin(self.execute(request).map_err(BoxError::from))
+    }
+}
+
+impl<F, Fut> Transport for F
+where
+    F: Fn(Request) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Response, BoxError>> + Send + 'static,
+{
+    fn send(&self, request: Request) -> BoxFuture<Result<Response, BoxError>> {
+        Box::pin(self(request))
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct SharedTransport(Arc<dyn Transport>);
+
+impl SharedTransport {
+    pub(crate) fn new<T: Transport>(transport: T) -> SharedTransport {
+        SharedTransport(Arc::new(transport))
+    }
+
+    pub(crate) fn send(&self, request: Request) -> BoxFuture<Result<Response, BoxError>> {
+        self.0.send(request)
+    }
+}
+
+impl fmt::Debug for SharedTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Transport")
+    }
+}
+
+/// Converts a request `Body` into a stream of bytes.
+///
+/// Useful for transports which need to forward the body of a request, e.g. the streaming
+/// multipart body of a file upload, to a different HTTP stack.
+pub fn body_stream(body: Body) -> impl Stream<Item = Result<Bytes, BoxError>> {
+    Response::from(http::Response::new(body))
+        .bytes_stream()
+        .map_err(BoxError::from)
+}
+
+type Handler = Box<dyn Fn(Request) -> http::Response<Body> + Send + Sync>;
+
+/// An in-memory [`Transport`] that answers requests with registered handlers.
+///
+/// Handlers are matched by the method and the path of the request url, the query string is
+/// ignored. Requests without a matching handler fail with a request error.
+///
+/// # Example
+/// ```
+/// use modio::transport::MemoryTransport;
+/// # use modio::Modio;
+/// use http::{Method, StatusCode};
+///
+/// # #[tokio::main]
+/// # async fn main() -> modio::Result<()> {
+/// let transport = MemoryTransport::new().json(
+///     Method::POST,
+///     "/v1/oauth/emailrequest",
+///     StatusCode::OK,
+///     r#"{"code": 200, "message": "Email sent"}"#,
+/// );
+/// let modio = Modio::builder("api-key").transport(transport).build()?;
+///
+/// modio.auth().request_code("john@example.com").await?;
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct MemoryTransport {
+    routes: Vec<(Method, String, Handler)>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> MemoryTransport {
+        MemoryTransport::default()
+    }
+
+    /// Register a handler for requests with the given method and path.
+    #[must_use]
+    pub fn route<S, F, B>(mut self, method: Method, path: S, handler: F) -> MemoryTransport
+    where
+        S: Into<String>,
+        F: Fn(Request) -> http::Response<B> + Send + Sync + 'static,
+        B: Into<Body>,
+    {
+        let handler = move |req| handler(req).map(Into::into);
+        self.routes
+            .push((method, path.into(), Box::new(handler) as Handler));
+        self
+    }
+
+    /// Register a JSON response for requests with the given method and path.
+    #[must_use]
+    pub fn json<S, B>(self, method: Method, path: S, status: StatusCode, body: B) -> MemoryTransport
+    where
+        S: Into<String>,
+        B: Into<String>,
+    {
+        let body = body.into();
+        self.route(method, path, move |_| {
+            http::Response::builder()
+                .status(status)
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(body.clone())
+                .expect("valid response")
+        })
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send(&self, request: Request) -> BoxFuture<Result<Response, BoxError>> {
+        let handler = self
+            .routes
+            .iter()
+            .find(|(method, path, _)| method == request.method() && path == request.url().path());
+
+        let result = match handler {
+            Some((_, _, handler)) => Ok(Response::from(handler(request))),
+            None => Err(BoxError::from(format!(
+                "no route for {} {}",
+                request.method(),
+                request.url().path()
+            ))),
+        };
+        Box::pin(future::ready(result))
+    }
+}
+
+impl fmt::Debug for MemoryTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.routes.iter().map(|(m, p, _)| format!("{} {}", m, p)))
+            .finish()
+    }
+}
+
+#[cfg(feature = "tower")]
+mod service {
+    use std::fmt;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+
+    use tokio::sync::Mutex;
+    use tower::{Service, ServiceExt};
+
+    use super::{BoxError, BoxFuture, Request, Response, SharedTransport, Transport};
+
+    /// The transport of a `Modio` client as [`tower::Service`].
+    ///
+    /// This is the innermost service of the stack built with
+    /// [`Builder::layer`](crate::Builder::layer).
+    #[derive(Clone)]
+    pub struct TransportService {
+        transport: SharedTransport,
+    }
+
+    impl TransportService {
+        pub(crate) fn new(transport: SharedTransport) -> TransportService {
+            TransportService { transport }
+        }
+    }
+
+    impl Service<Request> for TransportService {
+        type Response = Response;
+        type Error = BoxError;
+        type Future = BoxFuture<Result<Response, BoxError>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request) -> Self::Future {
+            self.transport.send(request)
+        }
+    }
+
+    impl fmt::Debug for TransportService {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("TransportService")
+        }
+    }
+
+    /// A [`Transport`] that sends requests with a [`tower::Service`].
+    ///
+    /// All requests share the single service, so the state of layers like rate limits applies
+    /// to all requests. The service is driven to readiness by one request at a time before the
+    /// request is dispatched, the responses are awaited concurrently.
+    pub struct ServiceTransport<S> {
+        service: Arc<Mutex<S>>,
+    }
+
+    impl<S> ServiceTransport<S> {
+        pub fn new(service: S) -> ServiceTransport<S> {
+            ServiceTransport {
+                service: Arc::new(Mutex::new(service)),
+            }
+        }
+    }
+
+    impl<S> Transport for ServiceTransport<S>
+    where
+        S: Service<Request, Response = Response> + Send + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        fn send(&self, request: Request) -> BoxFuture<Result<Response, BoxError>> {
+            let service = Arc::clone(&self.service);
+            Box::pin(async move {
+                let future = {
+                    let mut service = service.lock().await;
+                    let ready = service.ready().await.map_err(Into::into)?;
+                    ready.call(request)
+                };
+                future.await.map_err(Into::into)
+            })
+        }
+    }
+
+    impl<S> fmt::Debug for ServiceTransport<S> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("ServiceTransport")
+        }
+    }
+}
+
+#[cfg(feature = "tower")]
+pub use service::{ServiceTransport, TransportService};
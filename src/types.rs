@@ -30,6 +30,7 @@ pub struct AccessToken {
 /// See the [Message Object](https://docs.mod.io/#message-object) docs for more information.
 #[derive(Debug, Deserialize)]
 #[non_exhaustive]
+#[allow(dead_code)]
 pub struct Message {
     pub code: u16,
     pub message: String,
@@ -90,6 +91,7 @@ pub struct ErrorResponse {
 /// See the [Error Object](https://docs.mod.io/#error-object) docs for more information.
 #[derive(Debug, Deserialize)]
 #[non_exhaustive]
+#[allow(dead_code)]
 pub struct Error {
     pub code: u16,
     pub error_ref: u16,
@@ -180,7 +182,7 @@ enum_number! {
 }
 
 /// See the [mod.io docs](https://docs.mod.io/#targeting-a-platform) for more information.
-#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 #[non_exhaustive]
 pub enum TargetPlatform {
@@ -8,7 +8,7 @@ use crate::types::{DeserializeField, MissingField};
 use crate::TargetPlatform;
 
 /// See the [Modfile Object](https://docs.mod.io/#modfile-object) docs for more information.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[non_exhaustive]
 pub struct File {
     pub id: u32,
@@ -45,7 +45,8 @@ impl<'de> Deserialize<'de> for File {
             MetadataBlob,
             Download,
             Platforms,
-            Other(String),
+            #[serde(other)]
+            Other,
         }
 
         struct FileVisitor;
@@ -121,7 +122,7 @@ impl<'de> Deserialize<'de> for File {
                         Field::Platforms => {
                             platforms.deserialize_value("platforms", &mut map)?;
                         }
-                        Field::Other(_) => {
+                        Field::Other => {
                             map.next_value::<IgnoredAny>()?;
                         }
                     }
@@ -170,24 +171,65 @@ impl<'de> Deserialize<'de> for File {
 }
 
 /// See the [Modfile Object](https://docs.mod.io/#modfile-object) docs for more information.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[non_exhaustive]
 pub struct VirusScan {
     pub date_scanned: u64,
-    pub status: u32,
-    pub result: u32,
+    pub status: VirusStatus,
+    pub result: VirusResult,
     pub virustotal_hash: Option<String>,
 }
 
+impl VirusScan {
+    /// Returns `true` if the scan has completed.
+    pub fn is_scanned(&self) -> bool {
+        matches!(self.status, VirusStatus::ScanComplete)
+    }
+
+    /// Returns `true` if the file was flagged as malicious or potentially harmful.
+    pub fn is_flagged(&self) -> bool {
+        !matches!(self.result, VirusResult::NoThreats)
+    }
+}
+
+enum_number! {
+    /// Status of the virus scan of a modfile.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+    #[serde(from = "u32")]
+    #[non_exhaustive]
+    pub enum VirusStatus {
+        NotScanned = 0,
+        ScanComplete = 1,
+        InProgress = 2,
+        TooLargeToScan = 3,
+        FileNotFound = 4,
+        ErrorScanning = 5,
+        _ => Unknown(u32),
+    }
+}
+
+enum_number! {
+    /// Result of the virus scan of a modfile.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+    #[serde(from = "u32")]
+    #[non_exhaustive]
+    pub enum VirusResult {
+        NoThreats = 0,
+        Malicious = 1,
+        PotentiallyHarmful = 2,
+        _ => Unknown(u32),
+    }
+}
+
 /// See the [Filehash Object](https://docs.mod.io/#filehash-object) docs for more information.
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[non_exhaustive]
 pub struct FileHash {
     pub md5: String,
 }
 
 /// See the [Download Object](https://docs.mod.io/#download-object) docs for more information.
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 #[non_exhaustive]
 pub struct Download {
     pub binary_url: Url,
@@ -205,7 +247,7 @@ impl fmt::Debug for Download {
 
 /// See the [Modfile Platform Object](https://docs.mod.io/#modfile-platform-object) docs for more
 /// information.
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[non_exhaustive]
 pub struct Platform {
     #[serde(rename = "platform")]
@@ -226,3 +268,39 @@ enum_number! {
         _ => Unknown(u8),
     }
 }
+
+/// See the [Multipart Upload Object](https://docs.mod.io/#multipart-upload-object) docs for more
+/// information.
+#[derive(Clone, Debug, Deserialize)]
+#[non_exhaustive]
+pub struct UploadSession {
+    pub upload_id: String,
+    pub status: UploadStatus,
+}
+
+enum_number! {
+    /// See the [Multipart Upload Object](https://docs.mod.io/#multipart-upload-object) docs for
+    /// more information.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+    #[serde(from = "u8")]
+    #[non_exhaustive]
+    pub enum UploadStatus {
+        Incomplete = 0,
+        Pending = 1,
+        Processing = 2,
+        Completed = 3,
+        Cancelled = 4,
+        _ => Unknown(u8),
+    }
+}
+
+/// See the [Multipart Upload Part Object](https://docs.mod.io/#multipart-upload-part-object) docs
+/// for more information.
+#[derive(Clone, Debug, Deserialize)]
+#[non_exhaustive]
+pub struct UploadPart {
+    pub upload_id: String,
+    pub part_number: u32,
+    pub part_size: u64,
+    pub date_added: u64,
+}
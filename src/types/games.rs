@@ -206,7 +206,8 @@ impl<'de> Deserialize<'de> for Statistics {
             ModsDownloadsToday,
             ModsDownloadsDailyAverage,
             DateExpires,
-            Other(String),
+            #[serde(other)]
+            Other,
         }
 
         struct StatisticsVisitor;
@@ -252,7 +253,7 @@ impl<'de> Deserialize<'de> for Statistics {
                         Field::DateExpires => {
                             expired_at.deserialize_value("date_expires", &mut map)?;
                         }
-                        Field::Other(_) => {
+                        Field::Other => {
                             map.next_value::<IgnoredAny>()?;
                         }
                     }
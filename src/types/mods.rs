@@ -220,7 +220,8 @@ impl<'de> Deserialize<'de> for Statistics {
             RatingsWeightedAggregate,
             RatingsDisplayText,
             DateExpires,
-            Other(String),
+            #[serde(other)]
+            Other,
         }
 
         struct StatisticsVisitor;
@@ -292,7 +293,7 @@ impl<'de> Deserialize<'de> for Statistics {
                         Field::DateExpires => {
                             date_expires.deserialize_value("date_expires", &mut map)?;
                         }
-                        Field::Other(_) => {
+                        Field::Other => {
                             map.next_value::<IgnoredAny>()?;
                         }
                     }
@@ -0,0 +1,926 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::stream;
+use http::Method;
+use httptest::{all_of, cycle, Expectation, Server};
+use httptest::{matchers::*, responders::*};
+use serde_json::json;
+
+use modio::download::{DownloadAction, DownloadCache, ResolvePolicy, Throttle, VirusScanPolicy};
+use modio::files::{File, VirusResult};
+use modio::transport::{Body, MemoryTransport, Request, Transport};
+use modio::{CancelHandle, Modio, Result, TargetPlatform};
+
+const CONTENT: &[u8] = b"hello world";
+const DOWNLOAD_PATH: &str = "/v1/games/1/mods/2/files/3/download/abc";
+
+fn file(url: &str) -> serde_json::Value {
+    json!({
+        "id": 3,
+        "mod_id": 2,
+        "date_added": 0,
+        "date_scanned": 0,
+        "virus_status": 1,
+        "virus_positive": 0,
+        "virustotal_hash": null,
+        "filesize": CONTENT.len(),
+        "filehash": {"md5": format!("{:x}", md5::compute(CONTENT))},
+        "filename": "mod.zip",
+        "version": null,
+        "changelog": null,
+        "metadata_blob": null,
+        "download": {"binary_url": url, "date_expires": 0},
+        "platforms": [],
+    })
+}
+
+fn file_obj(url: &str) -> File {
+    serde_json::from_value(file(url)).expect("valid modfile")
+}
+
+fn range(start: usize) -> impl Responder {
+    status_code(206)
+        .insert_header(
+            "content-range",
+            format!("bytes {}-{}/{}", start, CONTENT.len() - 1, CONTENT.len()),
+        )
+        .body(&CONTENT[start..])
+}
+
+#[tokio::test]
+async fn resume_partial_download() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", DOWNLOAD_PATH),
+            request::headers(contains(("range", "bytes=5-"))),
+        ])
+        .respond_with(range(5)),
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mod.zip");
+    std::fs::write(&path, &CONTENT[..5]).unwrap();
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let action = DownloadAction::from(file_obj(&server.url_str(DOWNLOAD_PATH)));
+    modio
+        .download(action)
+        .resume(true)
+        .save_to_file(&path)
+        .await?;
+
+    assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+    Ok(())
+}
+
+#[tokio::test]
+async fn resume_ignored_by_server() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", DOWNLOAD_PATH))
+            .respond_with(status_code(200).body(CONTENT)),
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mod.zip");
+    std::fs::write(&path, b"hello").unwrap();
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let action = DownloadAction::from(file_obj(&server.url_str(DOWNLOAD_PATH)));
+    modio
+        .download(action)
+        .resume(true)
+        .save_to_file(&path)
+        .await?;
+
+    assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+    Ok(())
+}
+
+#[tokio::test]
+async fn resume_completed_download() -> Result<()> {
+    let server = Server::run();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mod.zip");
+    std::fs::write(&path, CONTENT).unwrap();
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let action = DownloadAction::from(file_obj(&server.url_str(DOWNLOAD_PATH)));
+    modio
+        .download(action)
+        .resume(true)
+        .save_to_file(&path)
+        .await?;
+
+    assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+    Ok(())
+}
+
+#[tokio::test]
+async fn resume_completed_download_with_other_content() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", DOWNLOAD_PATH),
+            request::headers(not(contains(key("range")))),
+        ])
+        .respond_with(status_code(200).body(CONTENT)),
+    );
+
+    // A leftover file of another version with the same size.
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mod.zip");
+    std::fs::write(&path, vec![b'x'; CONTENT.len()]).unwrap();
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let action = DownloadAction::from(file_obj(&server.url_str(DOWNLOAD_PATH)));
+    modio
+        .download(action)
+        .resume(true)
+        .save_to_file(&path)
+        .await?;
+
+    assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+    Ok(())
+}
+
+#[tokio::test]
+async fn resume_with_expired_url() -> Result<()> {
+    let server = Server::run();
+    let renewed = "/v1/games/1/mods/2/files/3/download/def";
+    server.expect(
+        Expectation::matching(request::method_path("GET", DOWNLOAD_PATH))
+            .respond_with(status_code(410)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games/1/mods/2/files/3"))
+            .respond_with(json_encoded(file(&server.url_str(renewed)))),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", renewed),
+            request::headers(contains(("range", "bytes=6-"))),
+        ])
+        .respond_with(range(6)),
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mod.zip");
+    std::fs::write(&path, &CONTENT[..6]).unwrap();
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let action = DownloadAction::from(file_obj(&server.url_str(DOWNLOAD_PATH)));
+    modio
+        .download(action)
+        .resume(true)
+        .save_to_file(&path)
+        .await?;
+
+    assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+    Ok(())
+}
+
+#[tokio::test]
+async fn corrupted_download_is_removed() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", DOWNLOAD_PATH))
+            .times(2)
+            .respond_with(status_code(200).body("hello wörld")),
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mod.zip");
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let action = DownloadAction::from(file_obj(&server.url_str(DOWNLOAD_PATH)));
+    let err = modio.download(action).save_to_file(&path).await;
+    let err = err.expect_err("size mismatch");
+    assert!(err.is_download());
+    assert!(!path.exists());
+
+    let action = DownloadAction::from(file_obj(&server.url_str(DOWNLOAD_PATH)));
+    let bytes = modio.download(action).verify(false).bytes().await?;
+    assert_eq!(&bytes[..], "hello wörld".as_bytes());
+    Ok(())
+}
+
+#[tokio::test]
+async fn hash_mismatch() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", DOWNLOAD_PATH))
+            .respond_with(status_code(200).body("hello World")),
+    );
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let action = DownloadAction::from(file_obj(&server.url_str(DOWNLOAD_PATH)));
+    let err = modio
+        .download(action)
+        .bytes()
+        .await
+        .expect_err("hash mismatch");
+    assert!(err.is_download());
+    assert!(err.to_string().contains("hash mismatch"), "{}", err);
+    Ok(())
+}
+
+#[tokio::test]
+async fn progress_reporting() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", DOWNLOAD_PATH))
+            .respond_with(status_code(200).body(CONTENT)),
+    );
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let captured = Arc::clone(&events);
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let action = DownloadAction::from(file_obj(&server.url_str(DOWNLOAD_PATH)));
+    modio
+        .download(action)
+        .progress(move |p| captured.lock().unwrap().push(p.clone()))
+        .bytes()
+        .await?;
+
+    let events = events.lock().unwrap();
+    let last = events.last().expect("progress events");
+    assert_eq!(last.downloaded, CONTENT.len() as u64);
+    assert_eq!(last.total, Some(CONTENT.len() as u64));
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancel_and_resume() -> Result<()> {
+    let transport = MemoryTransport::new().route(Method::GET, "/download/mod.zip", |req| {
+        let offset = match req.headers().get("range") {
+            Some(range) => {
+                let range = range.to_str().unwrap();
+                range["bytes=".len()..range.len() - 1].parse().unwrap()
+            }
+            None => 0,
+        };
+        let chunks = CONTENT[offset..]
+            .chunks(6)
+            .map(|c| Ok::<_, io::Error>(Bytes::copy_from_slice(c)))
+            .collect::<Vec<_>>();
+        let builder = http::Response::builder().header(
+            "content-range",
+            format!("bytes {}-{}/{}", offset, CONTENT.len() - 1, CONTENT.len()),
+        );
+        let status = if offset > 0 { 206 } else { 200 };
+        builder
+            .status(status)
+            .body(Body::wrap_stream(stream::iter(chunks)))
+            .unwrap()
+    });
+    let modio = Modio::builder("foobar").transport(transport).build()?;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mod.zip");
+    let file = || file_obj("https://binary.modcdn.io/download/mod.zip");
+
+    let cancel = CancelHandle::new();
+    let handle = cancel.clone();
+    let err = modio
+        .download(file())
+        .progress(move |_| handle.cancel())
+        .cancel_handle(cancel)
+        .save_to_file(&path)
+        .await
+        .expect_err("cancelled");
+    assert!(err.is_cancelled());
+    assert_eq!(std::fs::read(&path).unwrap(), &CONTENT[..6]);
+
+    modio
+        .download(file())
+        .resume(true)
+        .save_to_file(&path)
+        .await?;
+    assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+    Ok(())
+}
+
+fn large_file(url: &str, content: &[u8]) -> File {
+    let mut file = file(url);
+    file["filesize"] = json!(content.len());
+    file["filehash"]["md5"] = json!(format!("{:x}", md5::compute(content)));
+    serde_json::from_value(file).expect("valid modfile")
+}
+
+/// Serves `content` with support for `Range: bytes=<start>-[<end>]` headers. The first request
+/// for a range starting at `fail_at` fails with a server error.
+fn ranged_transport(content: Arc<Vec<u8>>, fail_at: Option<usize>) -> MemoryTransport {
+    let failed = AtomicBool::new(false);
+    MemoryTransport::new().route(Method::GET, "/download/large.zip", move |req| {
+        let range = req.headers().get("range").map(|r| {
+            let r = r.to_str().unwrap().trim_start_matches("bytes=");
+            let (start, end) = r.split_once('-').unwrap();
+            let start: usize = start.parse().unwrap();
+            let end = end.parse().unwrap_or(content.len() - 1);
+            (start, end)
+        });
+        match range {
+            Some((start, _)) if Some(start) == fail_at && !failed.swap(true, Ordering::SeqCst) => {
+                http::Response::builder()
+                    .status(503)
+                    .body(Vec::new())
+                    .unwrap()
+            }
+            Some((start, end)) => http::Response::builder()
+                .status(206)
+                .header(
+                    "content-range",
+                    format!("bytes {}-{}/{}", start, end, content.len()),
+                )
+                .body(content[start..=end].to_vec())
+                .unwrap(),
+            None => http::Response::new(content.to_vec()),
+        }
+    })
+}
+
+#[tokio::test]
+async fn parallel_download() -> Result<()> {
+    let content = (0..3 * 1024 * 1024 + 7)
+        .map(|i| (i % 251) as u8)
+        .collect::<Vec<_>>();
+    let content = Arc::new(content);
+    let url = "https://binary.modcdn.io/download/large.zip";
+
+    let second_range = content.len() / 3 + 1;
+    let transport = ranged_transport(Arc::clone(&content), Some(second_range));
+    let modio = Modio::builder("foobar").transport(transport).build()?;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("large.zip");
+
+    let downloaded = Arc::new(Mutex::new(0));
+    let captured = Arc::clone(&downloaded);
+    modio
+        .download(large_file(url, &content))
+        .parallel(4)
+        .progress(move |p| *captured.lock().unwrap() = p.downloaded)
+        .save_to_file(&path)
+        .await?;
+
+    assert_eq!(std::fs::read(&path).unwrap(), *content);
+    assert_eq!(*downloaded.lock().unwrap(), content.len() as u64);
+    Ok(())
+}
+
+#[tokio::test]
+async fn parallel_download_without_range_support() -> Result<()> {
+    let content = Arc::new(vec![7; 2 * 1024 * 1024]);
+    let url = "https://binary.modcdn.io/download/large.zip";
+
+    let inner = ranged_transport(Arc::clone(&content), None);
+    let transport = move |mut req: Request| {
+        req.headers_mut().remove("range");
+        inner.send(req)
+    };
+    let modio = Modio::builder("foobar").transport(transport).build()?;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("large.zip");
+    modio
+        .download(large_file(url, &content))
+        .parallel(4)
+        .save_to_file(&path)
+        .await?;
+
+    assert_eq!(std::fs::read(&path).unwrap(), *content);
+    Ok(())
+}
+
+#[tokio::test]
+async fn save_to_dir() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", DOWNLOAD_PATH))
+            .times(2)
+            .respond_with(cycle![
+                status_code(200).body(CONTENT),
+                status_code(200).body("hello World"),
+            ]),
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut file = file(&server.url_str(DOWNLOAD_PATH));
+    file["filename"] = json!("../../mod.zip");
+    let file = serde_json::from_value::<File>(file).unwrap();
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let (path, resolved) = modio.download(file.clone()).save_to_dir(dir.path()).await?;
+    assert_eq!(path, dir.path().join("mod.zip"));
+    assert_eq!(resolved.id, 3);
+    assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+
+    std::fs::remove_file(&path).unwrap();
+    let err = modio.download(file).save_to_dir(dir.path()).await;
+    assert!(err.expect_err("hash mismatch").is_download());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn resolve_before_download() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games/1/mods/2/files/3"))
+            .times(1)
+            .respond_with(json_encoded(file(&server.url_str(DOWNLOAD_PATH)))),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", DOWNLOAD_PATH))
+            .respond_with(status_code(200).body(CONTENT)),
+    );
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let download = modio.download((1, 2, 3)).resolve().await?;
+    assert_eq!(download.file().filesize, CONTENT.len() as u64);
+    assert_eq!(download.file().filename, "mod.zip");
+
+    let bytes = download.bytes().await?;
+    assert_eq!(&bytes[..], CONTENT);
+    Ok(())
+}
+
+#[tokio::test]
+async fn cached_download() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", DOWNLOAD_PATH))
+            .times(1)
+            .respond_with(status_code(200).body(CONTENT)),
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let cache = DownloadCache::new(dir.path().join("cache")).hardlink(true);
+    let file = file_obj(&server.url_str(DOWNLOAD_PATH));
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    for profile in &["a", "b"] {
+        let path = dir.path().join(profile);
+        std::fs::create_dir(&path).unwrap();
+        let (path, _) = modio
+            .download(file.clone())
+            .cache(cache.clone())
+            .save_to_dir(&path)
+            .await?;
+        assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+    }
+
+    let entries = cache.entries().await?;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].md5, file.filehash.md5);
+    assert_eq!(entries[0].size, CONTENT.len() as u64);
+    assert_eq!(cache.size().await?, CONTENT.len() as u64);
+
+    assert!(cache.remove(&file.filehash.md5).await?);
+    assert!(cache.get(&file.filehash.md5).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn cache_eviction() -> Result<()> {
+    let contents: [&[u8]; 3] = [b"first file", b"second file", b"third file"];
+    let mut transport = MemoryTransport::new();
+    for (i, content) in contents.iter().enumerate() {
+        let content = content.to_vec();
+        transport = transport.route(Method::GET, format!("/{}.zip", i), move |_| {
+            http::Response::new(content.clone())
+        });
+    }
+    let modio = Modio::builder("foobar").transport(transport).build()?;
+
+    let dir = tempfile::tempdir().unwrap();
+    let cache = DownloadCache::new(dir.path().join("cache")).max_size(25);
+    let files = contents
+        .iter()
+        .enumerate()
+        .map(|(i, content)| {
+            let mut file = file(&format!("https://binary.modcdn.io/{}.zip", i));
+            file["id"] = json!(i);
+            file["filesize"] = json!(content.len());
+            file["filehash"]["md5"] = json!(format!("{:x}", md5::compute(content)));
+            serde_json::from_value::<File>(file).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let path = dir.path().join("mod.zip");
+    for file in &files {
+        modio
+            .download(file.clone())
+            .cache(cache.clone())
+            .save_to_file(&path)
+            .await?;
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    // The least recently used file is evicted.
+    let cached = cache.entries().await?;
+    let cached = cached.iter().map(|e| &*e.md5).collect::<Vec<_>>();
+    assert_eq!(cached, [&*files[2].filehash.md5, &*files[1].filehash.md5]);
+
+    // The last use is recorded next to the cached files.
+    assert!(cache
+        .dir()
+        .join(format!(".{}.used", files[2].filehash.md5))
+        .exists());
+    assert!(!cache
+        .dir()
+        .join(format!(".{}.used", files[0].filehash.md5))
+        .exists());
+
+    let partial = cache
+        .dir()
+        .join(format!(".{}.1-0.part", files[0].filehash.md5));
+    std::fs::write(&partial, b"").unwrap();
+    assert_eq!(cache.gc().await?, 0);
+    assert!(!partial.exists());
+    assert_eq!(std::fs::read_dir(cache.dir()).unwrap().count(), 4);
+
+    cache.clear().await?;
+    assert!(cache.entries().await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn throttled_download() -> Result<()> {
+    let content = Arc::new(vec![7u8; 300]);
+    let body = Arc::clone(&content);
+    let transport = MemoryTransport::new().route(Method::GET, "/download/large.zip", move |_| {
+        let chunks = body
+            .chunks(100)
+            .map(|c| Ok::<_, io::Error>(Bytes::copy_from_slice(c)))
+            .collect::<Vec<_>>();
+        http::Response::new(Body::wrap_stream(stream::iter(chunks)))
+    });
+    let throttle = Throttle::new(100);
+    let modio = Modio::builder("foobar")
+        .transport(transport)
+        .throttle(throttle.clone())
+        .build()?;
+    let file = large_file("https://binary.modcdn.io/download/large.zip", &content);
+
+    // The first chunk fits into the bucket, the others are held back for a second each.
+    let started = tokio::time::Instant::now();
+    let bytes = modio.download(file.clone()).bytes().await?;
+    assert_eq!(bytes.len(), 300);
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_secs(2) && elapsed < Duration::from_secs(3));
+
+    // The limit of the download applies in addition to the limit of the client.
+    throttle.set_limit(Some(1_000_000));
+    let started = tokio::time::Instant::now();
+    modio
+        .download(file.clone())
+        .throttle(Throttle::new(50))
+        .bytes()
+        .await?;
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_secs(5) && elapsed < Duration::from_secs(6));
+
+    throttle.set_limit(None);
+    let started = tokio::time::Instant::now();
+    modio.download(file).bytes().await?;
+    assert!(started.elapsed() < Duration::from_secs(1));
+    Ok(())
+}
+
+fn mod_obj(modfile: serde_json::Value, platforms: serde_json::Value) -> serde_json::Value {
+    let url = "https://mod.io/g/game/m/mod";
+    let image = "https://thumb.modcdn.io/logo.png";
+    json!({
+        "id": 2,
+        "game_id": 1,
+        "status": 1,
+        "visible": 1,
+        "submitted_by": {
+            "id": 1,
+            "name_id": "user",
+            "username": "user",
+            "date_online": 0,
+            "avatar": {},
+            "profile_url": "https://mod.io/u/user",
+        },
+        "date_added": 0,
+        "date_updated": 0,
+        "date_live": 0,
+        "maturity_option": 0,
+        "logo": {
+            "filename": "logo.png",
+            "original": image,
+            "thumb_320x180": image,
+            "thumb_640x360": image,
+            "thumb_1280x720": image,
+        },
+        "homepage_url": null,
+        "name": "Mod",
+        "name_id": "mod",
+        "summary": "",
+        "description": null,
+        "description_plaintext": null,
+        "metadata_blob": null,
+        "profile_url": url,
+        "modfile": modfile,
+        "media": {},
+        "metadata_kvp": [],
+        "tags": [],
+        "stats": {
+            "mod_id": 2,
+            "downloads_today": 0,
+            "downloads_total": 0,
+            "subscribers_total": 0,
+            "popularity_rank_position": 0,
+            "popularity_rank_total_mods": 0,
+            "ratings_total": 0,
+            "ratings_positive": 0,
+            "ratings_negative": 0,
+            "ratings_percentage_positive": 0,
+            "ratings_weighted_aggregate": 0.0,
+            "ratings_display_text": "",
+            "date_expires": 0,
+        },
+        "platforms": platforms,
+    })
+}
+
+#[tokio::test]
+async fn primary_file_for_target_platform() -> Result<()> {
+    let server = Server::run();
+    let url = server.url_str(DOWNLOAD_PATH);
+
+    let mut windows = file(&url);
+    windows["platforms"] = json!([{"platform": "windows", "status": 1}]);
+    let mut linux = file(&url);
+    linux["id"] = json!(4);
+    linux["platforms"] = json!([{"platform": "linux", "status": 1}]);
+    let platforms = json!([
+        {"platform": "windows", "modfile_live": 3},
+        {"platform": "linux", "modfile_live": 4},
+    ]);
+
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", "/v1/games/1/mods/2"),
+            request::headers(contains(("x-modio-platform", "Linux"))),
+        ])
+        .respond_with(json_encoded(mod_obj(windows, platforms))),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games/1/mods/2/files/4"))
+            .respond_with(json_encoded(linux)),
+    );
+
+    let modio = Modio::builder("foobar")
+        .host(server.url_str("/v1"))
+        .target_platform(TargetPlatform::Linux)
+        .build()?;
+    let download = modio.download((1, 2)).resolve().await?;
+    assert_eq!(download.file().id, 4);
+    Ok(())
+}
+
+#[tokio::test]
+async fn no_approved_file_for_target_platform() -> Result<()> {
+    let server = Server::run();
+    let url = server.url_str(DOWNLOAD_PATH);
+
+    let mut pending = file(&url);
+    pending["platforms"] = json!([{"platform": "linux", "status": 0}]);
+    let live = json!([{"platform": "linux", "modfile_live": 3}]);
+    let windows = json!([{"platform": "windows", "modfile_live": 3}]);
+
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games/1/mods/2"))
+            .times(2)
+            .respond_with(cycle![
+                json_encoded(mod_obj(pending.clone(), live)),
+                json_encoded(mod_obj(pending, windows)),
+            ]),
+    );
+
+    let modio = Modio::builder("foobar")
+        .host(server.url_str("/v1"))
+        .target_platform(TargetPlatform::Linux)
+        .build()?;
+    for _ in 0..2 {
+        let err = match modio.download((1, 2)).resolve().await {
+            Ok(_) => panic!("no approved file"),
+            Err(e) => e,
+        };
+        assert!(err.is_download());
+        assert_eq!(
+            err.to_string(),
+            "download error: Mod {id: 2, game_id: 1}: No approved file for platform 'Linux'."
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn semver_version_requirement() -> Result<()> {
+    let server = Server::run();
+    let url = server.url_str(DOWNLOAD_PATH);
+    let files = [
+        (10, "1.3.9"),
+        (11, "v1.4"),
+        (12, "1.6.0-beta"),
+        (13, "1.5.2"),
+        (14, "2.0.0"),
+        (15, "nightly"),
+    ]
+    .iter()
+    .rev()
+    .map(|(id, version)| {
+        let mut file = file(&url);
+        file["id"] = json!(id);
+        file["version"] = json!(version);
+        file
+    })
+    .collect::<Vec<_>>();
+    let page = json!({
+        "data": files,
+        "result_count": files.len(),
+        "result_offset": 0,
+        "result_limit": 100,
+        "result_total": files.len(),
+    });
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", "/v1/games/1/mods/2/files"),
+            request::query(url_decoded(contains(("_sort", "-date_added")))),
+        ])
+        .times(7)
+        .respond_with(json_encoded(page)),
+    );
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let resolve = |req: &str, policy| {
+        let action = DownloadAction::VersionReq {
+            game_id: 1,
+            mod_id: 2,
+            req: req.parse().unwrap(),
+            policy,
+        };
+        let download = modio.download(action);
+        async move { download.resolve().await.map(|d| d.file().id) }
+    };
+
+    assert_eq!(resolve("^1.4", ResolvePolicy::Latest).await?, 13);
+    assert_eq!(resolve("~1.4", ResolvePolicy::Latest).await?, 11);
+    assert_eq!(resolve("^1.4", ResolvePolicy::Prerelease).await?, 12);
+    assert_eq!(resolve("<1.6", ResolvePolicy::Prerelease).await?, 12);
+
+    // `1.6.0-beta` is older than any `1.6` release.
+    let err = resolve("^1.6", ResolvePolicy::Prerelease)
+        .await
+        .unwrap_err();
+    assert!(err.is_download());
+
+    let err = resolve("^3", ResolvePolicy::Latest).await.unwrap_err();
+    assert!(err.is_download());
+    let err = resolve("^1.4", ResolvePolicy::FailOnInvalid).await;
+    assert_eq!(
+        err.unwrap_err().to_string(),
+        "download error: Mod {id: 2, game_id: 1}: File { id: 15 } has an invalid version 'nightly'."
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn virus_scan_policy() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", DOWNLOAD_PATH))
+            .times(2)
+            .respond_with(status_code(200).body(CONTENT)),
+    );
+    let url = server.url_str(DOWNLOAD_PATH);
+    let scanned = |status: u32, positive: u32| {
+        let mut file = file(&url);
+        file["virus_status"] = json!(status);
+        file["virus_positive"] = json!(positive);
+        serde_json::from_value::<File>(file).expect("valid modfile")
+    };
+
+    let modio = Modio::builder("foobar")
+        .host(server.url_str("/v1"))
+        .virus_scan_policy(VirusScanPolicy::RequireScanned)
+        .build()?;
+    let download = |file, policy| {
+        let mut download = modio.download(DownloadAction::FileObj(Box::new(file)));
+        if let Some(policy) = policy {
+            download = download.virus_scan_policy(policy);
+        }
+        async move { download.bytes().await }
+    };
+
+    assert_eq!(&download(scanned(1, 0), None).await?[..], CONTENT);
+
+    let err = download(scanned(2, 0), None).await.unwrap_err();
+    assert!(err.is_download());
+    assert_eq!(
+        err.to_string(),
+        "download error: Mod {id: 2}: File { id: 3 } refused, virus scan status: InProgress, result: NoThreats."
+    );
+    let policy = Some(VirusScanPolicy::RefuseFlagged);
+    let err = download(scanned(1, 1), policy).await.unwrap_err();
+    assert!(err.is_download());
+
+    let policy = Some(VirusScanPolicy::Warn);
+    assert_eq!(&download(scanned(0, 2), policy).await?[..], CONTENT);
+
+    // Unknown values beyond `u8` are kept.
+    let file = scanned(1, 300);
+    assert_eq!(file.virus_scan.result, VirusResult::Unknown(300));
+    assert!(file.virus_scan.is_flagged());
+    Ok(())
+}
+
+#[tokio::test]
+async fn expired_file_obj_is_resolved_again() -> Result<()> {
+    let server = Server::run();
+    let renewed = "/v1/games/1/mods/2/files/3/download/def";
+    server.expect(
+        Expectation::matching(request::method_path("GET", DOWNLOAD_PATH))
+            .times(0)
+            .respond_with(status_code(410)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games/1/mods/2/files/3"))
+            .respond_with(json_encoded(file(&server.url_str(renewed)))),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", renewed))
+            .respond_with(status_code(200).body(CONTENT)),
+    );
+
+    let mut expired = file_obj(&server.url_str(DOWNLOAD_PATH));
+    expired.download.date_expires = 1;
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let bytes = modio.download(expired).bytes().await?;
+    assert_eq!(&bytes[..], CONTENT);
+    Ok(())
+}
+
+#[tokio::test]
+async fn expired_url_without_game_id() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/mod.zip"))
+            .times(0)
+            .respond_with(status_code(410)),
+    );
+
+    let mut expired = file_obj(&server.url_str("/mod.zip"));
+    expired.download.date_expires = 1;
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let err = modio.download(expired).bytes().await.unwrap_err();
+    assert!(err.is_download());
+    assert!(err.to_string().contains("expired download url"), "{}", err);
+    Ok(())
+}
+
+#[tokio::test]
+async fn forbidden_download_url_is_resolved_again() -> Result<()> {
+    let server = Server::run();
+    let renewed = "/v1/games/1/mods/2/files/3/download/def";
+    server.expect(
+        Expectation::matching(request::method_path("GET", DOWNLOAD_PATH))
+            .times(2)
+            .respond_with(status_code(403)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games/1/mods/2/files/3"))
+            .times(2)
+            .respond_with(json_encoded(file(&server.url_str(renewed)))),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", renewed))
+            .times(2)
+            .respond_with(status_code(200).body(CONTENT)),
+    );
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let stale = file_obj(&server.url_str(DOWNLOAD_PATH));
+    let bytes = modio.download(stale.clone()).bytes().await?;
+    assert_eq!(&bytes[..], CONTENT);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mod.zip");
+    modio.download(stale).save_to_file(&path).await?;
+    assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+    Ok(())
+}
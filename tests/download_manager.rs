@@ -0,0 +1,186 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use http::{Method, StatusCode};
+use serde_json::json;
+
+use modio::download::{DownloadEvent, DownloadJob, DownloadManager, JobStatus};
+use modio::files::File;
+use modio::retry::{Backoff, RetryPolicy};
+use modio::transport::MemoryTransport;
+use modio::{Modio, Result};
+
+const CONTENT: &[u8] = b"hello world";
+
+fn file(name: &str) -> File {
+    serde_json::from_value(json!({
+        "id": 3,
+        "mod_id": 2,
+        "date_added": 0,
+        "date_scanned": 0,
+        "virus_status": 1,
+        "virus_positive": 0,
+        "virustotal_hash": null,
+        "filesize": CONTENT.len(),
+        "filehash": {"md5": format!("{:x}", md5::compute(CONTENT))},
+        "filename": name,
+        "version": null,
+        "changelog": null,
+        "metadata_blob": null,
+        "download": {"binary_url": format!("https://binary.modcdn.io/{}", name), "date_expires": 0},
+        "platforms": [],
+    }))
+    .unwrap()
+}
+
+/// Serves every file and records the order of the requests.
+fn transport(names: &[&str], requests: &Arc<Mutex<Vec<String>>>) -> MemoryTransport {
+    names
+        .iter()
+        .fold(MemoryTransport::new(), |transport, name| {
+            let requests = Arc::clone(requests);
+            let name = name.to_string();
+            transport.route(Method::GET, format!("/{}", name), move |_| {
+                requests.lock().unwrap().push(name.clone());
+                http::Response::new(CONTENT)
+            })
+        })
+}
+
+#[tokio::test]
+async fn priorities_and_events() -> Result<()> {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let transport = transport(&["a.zip", "b.zip", "c.zip"], &requests);
+    let modio = Modio::builder("foobar").transport(transport).build()?;
+
+    let dir = tempfile::tempdir().unwrap();
+    let manager = DownloadManager::new(modio).concurrency(1);
+    let mut events = manager.events();
+
+    let a = manager.add(DownloadJob::to_dir(file("a.zip"), dir.path()));
+    let b = manager.add(DownloadJob::to_dir(file("b.zip"), dir.path()));
+    let c = manager.add(DownloadJob::to_dir(file("c.zip"), dir.path()).priority(10));
+    assert_eq!(manager.status(a), Some(JobStatus::Running));
+    assert_eq!(manager.status(b), Some(JobStatus::Queued));
+
+    manager.wait().await;
+    assert_eq!(*requests.lock().unwrap(), ["a.zip", "c.zip", "b.zip"]);
+    // Finished jobs are removed.
+    for id in [a, b, c] {
+        assert_eq!(manager.status(id), None);
+    }
+
+    // The events end once the manager is dropped.
+    drop(manager);
+    let mut finished = Vec::new();
+    let mut progress = 0;
+    while let Some(event) = events.next().await {
+        match event {
+            DownloadEvent::Progress { .. } => progress += 1,
+            DownloadEvent::Finished { id, path, file } => {
+                assert_eq!(path, dir.path().join(&file.filename));
+                assert_eq!(std::fs::read(path).unwrap(), CONTENT);
+                finished.push(id);
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(finished, [a, c, b]);
+    assert!(progress >= 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn retry_failed_jobs() -> Result<()> {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&attempts);
+    let transport = MemoryTransport::new()
+        .route(Method::GET, "/flaky.zip", move |_| {
+            if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                let mut resp = http::Response::new(&b""[..]);
+                *resp.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+                resp
+            } else {
+                http::Response::new(CONTENT)
+            }
+        })
+        .json(Method::GET, "/broken.zip", StatusCode::NOT_FOUND, "{}");
+    let modio = Modio::builder("foobar").transport(transport).build()?;
+
+    let dir = tempfile::tempdir().unwrap();
+    let retry = RetryPolicy::new(2).backoff(Backoff::fixed(Duration::from_millis(10)));
+    let manager = DownloadManager::new(modio).retry(retry);
+    let mut events = manager.events();
+
+    let flaky = manager.add(DownloadJob::to_file(
+        file("flaky.zip"),
+        dir.path().join("a"),
+    ));
+    manager.wait().await;
+    assert_eq!(manager.status(flaky), None);
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+    let broken = manager.add(DownloadJob::to_file(
+        file("broken.zip"),
+        dir.path().join("b"),
+    ));
+    manager.wait().await;
+    assert_eq!(manager.status(broken), None);
+
+    let mut log = Vec::new();
+    while let Some(event) = events.next().await {
+        match event {
+            DownloadEvent::Started { id, attempt } => {
+                log.push(format!("{} started {}", id, attempt))
+            }
+            DownloadEvent::Retrying { id, .. } => log.push(format!("{} retrying", id)),
+            DownloadEvent::Finished { id, .. } => log.push(format!("{} finished", id)),
+            DownloadEvent::Failed { id, .. } => {
+                log.push(format!("{} failed", id));
+                break;
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(
+        log,
+        [
+            "0 started 1",
+            "0 retrying",
+            "0 started 2",
+            "0 finished",
+            // Client errors aren't retried.
+            "1 started 1",
+            "1 failed"
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn pause_and_resume() -> Result<()> {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let transport = transport(&["a.zip", "b.zip"], &requests);
+    let modio = Modio::builder("foobar").transport(transport).build()?;
+
+    let dir = tempfile::tempdir().unwrap();
+    let manager = DownloadManager::new(modio).concurrency(1);
+
+    let a = manager.add(DownloadJob::to_dir(file("a.zip"), dir.path()));
+    let b = manager.add(DownloadJob::to_dir(file("b.zip"), dir.path()));
+    assert!(manager.pause(b));
+    assert!(!manager.resume(a));
+
+    manager.wait().await;
+    assert_eq!(manager.status(a), None);
+    assert_eq!(manager.status(b), Some(JobStatus::Paused));
+    assert_eq!(*requests.lock().unwrap(), ["a.zip"]);
+
+    assert!(manager.resume(b));
+    manager.wait().await;
+    assert_eq!(manager.status(b), None);
+    assert_eq!(std::fs::read(dir.path().join("b.zip")).unwrap(), CONTENT);
+    Ok(())
+}
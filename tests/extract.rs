@@ -0,0 +1,188 @@
+#![cfg(feature = "extract")]
+use std::io::{Cursor, Write};
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use http::Method;
+use zip::write::{FileOptions, ZipWriter};
+
+use modio::extract::Extractor;
+use modio::files::File;
+use modio::transport::MemoryTransport;
+use modio::{Modio, Result};
+
+fn zip(entries: &[(&str, &[u8])]) -> Bytes {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, content) in entries {
+        if name.ends_with('/') {
+            zip.add_directory(*name, FileOptions::default()).unwrap();
+        } else {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+    }
+    Bytes::from(zip.finish().unwrap().into_inner())
+}
+
+#[tokio::test]
+async fn extract_archive() -> Result<()> {
+    let archive = zip(&[
+        ("mod.json", b"{}"),
+        ("assets/", b""),
+        ("assets/textures/a.png", b"png"),
+    ]);
+    let dir = tempfile::tempdir().unwrap();
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let captured = Arc::clone(&events);
+    Extractor::new(dir.path().join("mod"))
+        .progress(move |p| captured.lock().unwrap().push(p.clone()))
+        .extract_bytes(archive)
+        .await?;
+
+    let root = dir.path().join("mod");
+    assert_eq!(std::fs::read(root.join("mod.json")).unwrap(), b"{}");
+    assert_eq!(
+        std::fs::read(root.join("assets/textures/a.png")).unwrap(),
+        b"png"
+    );
+
+    let events = events.lock().unwrap();
+    let last = events.last().unwrap();
+    assert_eq!(
+        (last.entries, last.total_entries, last.extracted),
+        (3, 3, 5)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn reject_path_traversal() -> Result<()> {
+    let dir = tempfile::tempdir().unwrap();
+    let extractor = Extractor::new(dir.path().join("mod"));
+
+    for name in &["../evil.txt", "a/../../evil.txt", "/etc/evil.txt"] {
+        let err = extractor.extract_bytes(zip(&[(name, b"evil")])).await;
+        let err = err.expect_err("unsafe path");
+        assert!(err.is_extract(), "{}", err);
+    }
+    assert!(!dir.path().join("evil.txt").exists());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn reject_escaping_symlinks() -> Result<()> {
+    let symlinks = |target: &str| {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.add_symlink("data/link", target, FileOptions::default())
+            .unwrap();
+        Bytes::from(zip.finish().unwrap().into_inner())
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let extractor = Extractor::new(dir.path().join("mod"));
+
+    extractor.extract_bytes(symlinks("../mod.json")).await?;
+    let link = dir.path().join("mod/data/link");
+    assert_eq!(
+        std::fs::read_link(link).unwrap().to_str(),
+        Some("../mod.json")
+    );
+
+    let extractor = Extractor::new(dir.path().join("escaping"));
+    let err = extractor.extract_bytes(symlinks("../../secret")).await;
+    assert!(err.expect_err("escaping symlink").is_extract());
+
+    // `d/l/l2` is created at `l2` through the first link and points outside.
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default();
+    zip.add_symlink("d/l", "..", options).unwrap();
+    zip.add_symlink("d/l/l2", "..", options).unwrap();
+    zip.start_file("d/l/l2/evil", options).unwrap();
+    zip.write_all(b"evil").unwrap();
+    let archive = Bytes::from(zip.finish().unwrap().into_inner());
+
+    let target = dir.path().join("chained");
+    let err = Extractor::new(&target).extract_bytes(archive).await;
+    assert!(err.expect_err("chained symlinks").is_extract());
+    assert!(!dir.path().join("evil").exists());
+    assert!(!target.exists());
+
+    // `..` after a symbolic link in the middle of the target.
+    let extractor = Extractor::new(dir.path().join("parent"));
+    let err = extractor.extract_bytes(symlinks("../data/link/../x")).await;
+    assert!(err.expect_err("parent after link").is_extract());
+    Ok(())
+}
+
+#[tokio::test]
+async fn enforce_limits() -> Result<()> {
+    let dir = tempfile::tempdir().unwrap();
+    let archive = zip(&[("a.bin", &[0; 1024]), ("b.bin", &[0; 1024])]);
+
+    let err = Extractor::new(dir.path())
+        .max_size(1500)
+        .extract_bytes(archive.clone())
+        .await;
+    let err = err.expect_err("too large");
+    assert!(err.to_string().contains("size exceeds"), "{}", err);
+    // The entry extracted before the limit was reached is removed.
+    assert!(!dir.path().join("a.bin").exists());
+
+    // Existing files overwritten before the limit was reached are kept.
+    std::fs::write(dir.path().join("a.bin"), b"existing").unwrap();
+    let err = Extractor::new(dir.path())
+        .max_size(1500)
+        .extract_bytes(archive.clone())
+        .await;
+    assert!(err.is_err());
+    assert!(dir.path().join("a.bin").exists());
+    assert!(!dir.path().join("b.bin").exists());
+
+    let err = Extractor::new(dir.path())
+        .max_entries(1)
+        .extract_bytes(archive)
+        .await;
+    let err = err.expect_err("too many entries");
+    assert!(err.to_string().contains("entries"), "{}", err);
+    Ok(())
+}
+
+#[tokio::test]
+async fn download_and_extract() -> Result<()> {
+    let archive = zip(&[("mod.json", b"{}")]);
+    let file = serde_json::from_value::<File>(serde_json::json!({
+        "id": 3,
+        "mod_id": 2,
+        "date_added": 0,
+        "date_scanned": 0,
+        "virus_status": 1,
+        "virus_positive": 0,
+        "virustotal_hash": null,
+        "filesize": archive.len(),
+        "filehash": {"md5": format!("{:x}", md5::compute(&archive))},
+        "filename": "mod.zip",
+        "version": null,
+        "changelog": null,
+        "metadata_blob": null,
+        "download": {"binary_url": "https://binary.modcdn.io/mod.zip", "date_expires": 0},
+        "platforms": [],
+    }))
+    .unwrap();
+
+    let transport = MemoryTransport::new().route(Method::GET, "/mod.zip", move |_| {
+        http::Response::new(archive.to_vec())
+    });
+    let modio = Modio::builder("foobar").transport(transport).build()?;
+
+    let dir = tempfile::tempdir().unwrap();
+    let extractor = Extractor::new(dir.path().join("mod"));
+    modio.download(file).extract_to(&extractor).await?;
+
+    assert_eq!(
+        std::fs::read(dir.path().join("mod/mod.json")).unwrap(),
+        b"{}"
+    );
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    Ok(())
+}
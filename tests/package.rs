@@ -0,0 +1,171 @@
+#![cfg(feature = "package")]
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use httptest::http::Request;
+use httptest::{all_of, Expectation, Server};
+use httptest::{matchers::*, responders::*};
+use serde_json::json;
+
+use modio::files::{AddFileOptions, Error, Package};
+use modio::{Credentials, Modio, Result};
+
+fn create_mod(dir: &Path) {
+    fs::create_dir_all(dir.join("scripts/lib")).unwrap();
+    fs::create_dir_all(dir.join(".git")).unwrap();
+    fs::write(dir.join("mod.json"), b"{}").unwrap();
+    fs::write(dir.join("scripts/init.lua"), b"print('init')").unwrap();
+    fs::write(dir.join("scripts/lib/util.lua"), b"return {}").unwrap();
+    fs::write(dir.join("scripts/init.lua.bak"), b"backup").unwrap();
+    fs::write(dir.join(".git/HEAD"), b"ref: refs/heads/main").unwrap();
+}
+
+#[tokio::test]
+async fn deterministic_package() -> Result<()> {
+    let dir = tempfile::tempdir().unwrap();
+    create_mod(&dir.path().join("a"));
+    create_mod(&dir.path().join("b"));
+
+    let first = Package::new(dir.path().join("a"))
+        .exclude(".git/**")
+        .exclude("*.bak")
+        .build()
+        .await?;
+    assert_eq!(first.filename(), "a.zip");
+    assert_eq!(
+        first.entries().collect::<Vec<_>>(),
+        ["mod.json", "scripts/init.lua", "scripts/lib/util.lua"]
+    );
+
+    // Same content written at a different time.
+    let second = Package::new(dir.path().join("b"))
+        .exclude(".git/**")
+        .exclude("*.bak")
+        .build()
+        .await?;
+    assert_eq!(first.md5(), second.md5());
+    assert_eq!(first.size(), second.size());
+
+    let lua = Package::new(dir.path().join("a"))
+        .include("scripts/**/*.lua")
+        .build()
+        .await?;
+    assert_eq!(
+        lua.entries().collect::<Vec<_>>(),
+        ["scripts/init.lua", "scripts/lib/util.lua"]
+    );
+    assert_ne!(first.md5(), lua.md5());
+    Ok(())
+}
+
+#[tokio::test]
+async fn upload_package() -> Result<()> {
+    let dir = tempfile::tempdir().unwrap();
+    create_mod(dir.path());
+    let package = Package::new(dir.path())
+        .filename("my-mod.zip")
+        .exclude(".git/**")
+        .build()
+        .await?;
+    let md5 = package.md5().to_string();
+    let size = package.size();
+
+    let server = Server::run();
+    let body = Arc::new(Mutex::new(Vec::new()));
+    let captured = Arc::clone(&body);
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", "/v1/games/1/mods/2/files"),
+            request::body(matches(format!("name=\"filehash\"\r\n\r\n{}\r\n", md5))),
+            request::body(matches("filename=\"my-mod.zip\"")),
+            move |req: &Request<Bytes>| {
+                captured.lock().unwrap().extend_from_slice(req.body());
+                true
+            },
+        ])
+        .respond_with(json_encoded(json!({
+            "id": 3,
+            "mod_id": 2,
+            "date_added": 0,
+            "date_scanned": 0,
+            "virus_status": 0,
+            "virus_positive": 0,
+            "virustotal_hash": null,
+            "filesize": size,
+            "filehash": {"md5": md5},
+            "filename": "my-mod.zip",
+            "version": null,
+            "changelog": null,
+            "metadata_blob": null,
+            "download": {"binary_url": "https://example.com/my-mod.zip", "date_expires": 0},
+            "platforms": [],
+        }))),
+    );
+
+    let modio = Modio::host(
+        server.url_str("/v1"),
+        Credentials::with_token("foobar", "token"),
+    )?;
+    let options = AddFileOptions::from(package);
+    modio.mod_(1, 2).files().add(options).await?;
+
+    // The streamed archive is the archive of the computed hash.
+    let body = body.lock().unwrap().clone();
+    let start = find(&body, b"PK\x03\x04").expect("zip archive");
+    let end = find(&body, b"PK\x05\x06").expect("end of central directory") + 22;
+    let archive = Bytes::copy_from_slice(&body[start..end]);
+    assert_eq!(archive.len() as u64, size);
+    assert_eq!(format!("{:x}", md5::compute(&archive)), md5);
+
+    #[cfg(feature = "extract")]
+    {
+        let target = dir.path().join("extracted");
+        modio::extract::Extractor::new(&target)
+            .extract_bytes(archive)
+            .await?;
+        assert_eq!(fs::read(target.join("mod.json")).unwrap(), b"{}");
+        assert_eq!(
+            fs::read(target.join("scripts/lib/util.lua")).unwrap(),
+            b"return {}"
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn modified_package() -> Result<()> {
+    let dir = tempfile::tempdir().unwrap();
+    create_mod(dir.path());
+    let package = Package::new(dir.path()).build().await?;
+    fs::write(dir.path().join("mod.json"), b"[]").unwrap();
+
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("POST", "/v1/games/1/mods/2/files"))
+            .times(..)
+            .respond_with(status_code(500)),
+    );
+
+    let modio = Modio::host(
+        server.url_str("/v1"),
+        Credentials::with_token("foobar", "token"),
+    )?;
+    let options = AddFileOptions::from(package);
+    let err = modio.mod_(1, 2).files().add(options).await.unwrap_err();
+
+    // The upload is aborted before the last chunk of the archive is sent.
+    assert!(err.is_upload());
+    let source = std::error::Error::source(&err).and_then(|e| e.downcast_ref::<Error>());
+    assert!(
+        matches!(source, Some(Error::PackageChanged { .. })),
+        "{:?}",
+        err
+    );
+    Ok(())
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
@@ -0,0 +1,282 @@
+#![cfg(feature = "publish")]
+use std::fs;
+
+use httptest::{all_of, Expectation, Server};
+use httptest::{matchers::*, responders::*};
+use serde_json::json;
+
+use modio::publish::{Change, Field, Manifest, Publisher};
+use modio::{Credentials, Modio, Result};
+
+/// End of central directory record of an empty zip archive.
+const EMPTY_ZIP: &[u8] = b"PK\x05\x06\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+
+const MANIFEST: &str = r#"
+game_id = 1
+name_id = "mod"
+name = "Mod"
+summary = "A new summary."
+logo = "logo.png"
+tags = ["Maps", "Weapons"]
+dependencies = [7]
+
+[metadata]
+difficulty = "hard"
+
+[modfile]
+path = "mod.zip"
+version = "1.0"
+"#;
+
+fn list(data: Vec<serde_json::Value>) -> serde_json::Value {
+    json!({
+        "data": data,
+        "result_count": data.len(),
+        "result_offset": 0,
+        "result_limit": 100,
+        "result_total": data.len(),
+    })
+}
+
+fn mod_obj() -> serde_json::Value {
+    let url = "https://mod.io/g/game/m/mod";
+    let image = "https://thumb.modcdn.io/logo.png";
+    json!({
+        "id": 2,
+        "game_id": 1,
+        "status": 1,
+        "visible": 1,
+        "submitted_by": {
+            "id": 1,
+            "name_id": "user",
+            "username": "user",
+            "date_online": 0,
+            "avatar": {},
+            "profile_url": "https://mod.io/u/user",
+        },
+        "date_added": 0,
+        "date_updated": 0,
+        "date_live": 0,
+        "maturity_option": 0,
+        "logo": {
+            "filename": "logo.png",
+            "original": image,
+            "thumb_320x180": image,
+            "thumb_640x360": image,
+            "thumb_1280x720": image,
+        },
+        "homepage_url": null,
+        "name": "Mod",
+        "name_id": "mod",
+        "summary": "",
+        "description": null,
+        "description_plaintext": null,
+        "metadata_blob": null,
+        "profile_url": url,
+        "modfile": {
+            "id": 3,
+            "mod_id": 2,
+            "date_added": 0,
+            "date_scanned": 0,
+            "virus_status": 1,
+            "virus_positive": 0,
+            "virustotal_hash": null,
+            "filesize": EMPTY_ZIP.len(),
+            "filehash": {"md5": format!("{:x}", md5::compute(EMPTY_ZIP))},
+            "filename": "mod.zip",
+            "version": "1.0",
+            "changelog": null,
+            "metadata_blob": null,
+            "download": {"binary_url": "https://example.com/mod.zip", "date_expires": 0},
+            "platforms": [],
+        },
+        "media": {},
+        "metadata_kvp": [{"metakey": "difficulty", "metavalue": "easy"}],
+        "tags": [{"name": "Maps", "date_added": 0}, {"name": "Old", "date_added": 0}],
+        "stats": {
+            "mod_id": 2,
+            "downloads_today": 0,
+            "downloads_total": 0,
+            "subscribers_total": 0,
+            "popularity_rank_position": 0,
+            "popularity_rank_total_mods": 0,
+            "ratings_total": 0,
+            "ratings_positive": 0,
+            "ratings_negative": 0,
+            "ratings_percentage_positive": 0,
+            "ratings_weighted_aggregate": 0.0,
+            "ratings_display_text": "",
+            "date_expires": 0,
+        },
+        "platforms": [],
+    })
+}
+
+fn message() -> impl httptest::responders::Responder {
+    json_encoded(json!({"code": 201, "message": "ok"}))
+}
+
+#[tokio::test]
+async fn reconcile_existing_mod() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", "/v1/me/mods"),
+            request::query(url_decoded(contains(("name_id", "mod")))),
+            request::query(url_decoded(contains(("game_id", "1")))),
+        ])
+        .respond_with(json_encoded(list(vec![mod_obj()]))),
+    );
+    server.expect(
+        Expectation::matching(request::method_path(
+            "GET",
+            "/v1/games/1/mods/2/dependencies",
+        ))
+        .respond_with(json_encoded(list(vec![]))),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("PUT", "/v1/games/1/mods/2"),
+            request::body(url_decoded(contains(("summary", "A new summary.")))),
+        ])
+        .respond_with(json_encoded(mod_obj())),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("DELETE", "/v1/games/1/mods/2/tags"),
+            request::body(url_decoded(contains(("tags[]", "Old")))),
+        ])
+        .respond_with(status_code(204)),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", "/v1/games/1/mods/2/tags"),
+            request::body(url_decoded(contains(("tags[]", "Weapons")))),
+        ])
+        .respond_with(message()),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("DELETE", "/v1/games/1/mods/2/metadatakvp"),
+            request::body(url_decoded(contains(("metadata[]", "difficulty:easy")))),
+        ])
+        .respond_with(status_code(204)),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", "/v1/games/1/mods/2/metadatakvp"),
+            request::body(url_decoded(contains(("metadata[]", "difficulty:hard")))),
+        ])
+        .respond_with(message()),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", "/v1/games/1/mods/2/dependencies"),
+            request::body(url_decoded(contains(("dependencies[]", "7")))),
+        ])
+        .respond_with(message()),
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("modio.toml");
+    fs::write(&path, MANIFEST).unwrap();
+    fs::write(dir.path().join("mod.zip"), EMPTY_ZIP).unwrap();
+
+    let modio = Modio::host(
+        server.url_str("/v1"),
+        Credentials::with_token("foobar", "token"),
+    )?;
+    let manifest = Manifest::from_file(&path).await?;
+    let plan = Publisher::new(modio, manifest).publish().await?;
+
+    // The modfile and the logo are unchanged.
+    assert_eq!(plan.mod_id(), Some(2));
+    assert_eq!(
+        plan.changes()[0],
+        Change::EditMod {
+            fields: vec![Field::Summary]
+        }
+    );
+    assert_eq!(
+        plan.to_string(),
+        "edit mod: summary\n\
+         delete tags: Old\n\
+         add tags: Weapons\n\
+         delete metadata: difficulty=easy\n\
+         add metadata: difficulty=hard\n\
+         add dependencies: 7"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn dry_run_new_mod() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/me/mods"))
+            .respond_with(json_encoded(list(vec![]))),
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("modio.json");
+    let manifest = json!({
+        "game_id": 1,
+        "name_id": "mod",
+        "name": "Mod",
+        "summary": "Summary",
+        "logo": "logo.png",
+        "tags": ["Maps"],
+        "modfile": {"path": "build", "exclude": ["*.bak"], "version": "1.0"},
+    });
+    fs::write(&path, manifest.to_string()).unwrap();
+    fs::create_dir(dir.path().join("build")).unwrap();
+    fs::write(dir.path().join("build/mod.json"), b"{}").unwrap();
+    fs::write(dir.path().join("build/mod.json.bak"), b"").unwrap();
+
+    let modio = Modio::host(
+        server.url_str("/v1"),
+        Credentials::with_token("foobar", "token"),
+    )?;
+    let manifest = Manifest::from_file(&path).await?;
+    let plan = Publisher::new(modio, manifest)
+        .dry_run(true)
+        .publish()
+        .await?;
+
+    // Nothing but the lookup of the mod is requested and the directory isn't packaged.
+    assert_eq!(plan.mod_id(), None);
+    let changes = plan.changes();
+    assert_eq!(changes.len(), 3);
+    assert_eq!(
+        changes[0],
+        Change::CreateMod {
+            name: "Mod".to_string()
+        }
+    );
+    assert_eq!(changes[1], Change::AddTags(vec!["Maps".to_string()]));
+    assert!(matches!(
+        changes[2],
+        Change::UploadModfile { ref path, ref version, md5: None }
+            if path.ends_with("build") && version.as_deref() == Some("1.0")
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_manifest() -> Result<()> {
+    let err = Manifest::from_toml("game_id = 1\nname = \"Mod\"").unwrap_err();
+    assert!(err.is_publish());
+
+    let modio = Modio::host(
+        "http://127.0.0.1:0/v1",
+        Credentials::with_token("foobar", "token"),
+    )?;
+    let manifest = Manifest::from_toml("game_id = 1\nname = \"Mod\"\nsummary = \"\"")?;
+    let err = Publisher::new(modio, manifest).publish().await.unwrap_err();
+    assert!(err.is_publish());
+    assert_eq!(
+        err.to_string(),
+        "publish error: manifest requires a `mod_id` or `name_id` to find the mod."
+    );
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use httptest::{matchers::*, responders::*};
+use httptest::{Expectation, Server};
+use tracing::subscriber::DefaultGuard;
+
+use modio::download::DownloadAction;
+use modio::filter::Filter;
+use modio::retry::{Backoff, RetryPolicy};
+use modio::{Credentials, Modio};
+
+const API_KEY: &str = "secret-api-key";
+const ACCESS_TOKEN: &str = "secret-access-token";
+const SIGNATURE: &str = "c489a0354111a4d76640d47f0cdcb294";
+
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    fn capture(&self) -> DefaultGuard {
+        let out = self.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_writer(move || out.clone())
+            .finish();
+        tracing::subscriber::set_default(subscriber)
+    }
+
+    fn assert_redacted(&self) {
+        let logs = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+        assert!(!logs.is_empty(), "no tracing output captured");
+        for secret in &[API_KEY, ACCESS_TOKEN, SIGNATURE] {
+            assert!(
+                !logs.contains(secret),
+                "`{}` found in logs:\n{}",
+                secret,
+                logs
+            );
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn api_key_and_tokens() -> modio::Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("POST", "/v1/oauth/emailexchange"))
+            .respond_with(status_code(200).body(format!(
+                r#"{{"code":200,"access_token":"{}","date_expires":1}}"#,
+                ACCESS_TOKEN
+            ))),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/me")).respond_with(
+            status_code(401)
+                .body(r#"{"error":{"code":401,"error_ref":11005,"message":"Unauthorized"}}"#),
+        ),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games"))
+            .respond_with(status_code(200).insert_header("set-cookie", API_KEY).body(
+            r#"{"data":[],"result_count":0,"result_offset":0,"result_limit":100,"result_total":0}"#,
+        )),
+    );
+
+    let output = Output::default();
+    let _guard = output.capture();
+
+    let modio = Modio::host(server.url_str("/v1"), API_KEY)?;
+    let creds = modio.auth().security_code("QWERT").await?;
+    assert_eq!(creds.token.as_ref().map(|t| &*t.value), Some(ACCESS_TOKEN));
+
+    let modio = modio.with_credentials(creds);
+    let err = modio.user().current().await.expect_err("unauthorized");
+    assert!(err.is_auth());
+
+    modio.games().search(Filter::default()).first_page().await?;
+
+    output.assert_redacted();
+    Ok(())
+}
+
+#[tokio::test]
+async fn request_errors() -> modio::Result<()> {
+    let output = Output::default();
+    let _guard = output.capture();
+
+    let policy = RetryPolicy::new(2).backoff(Backoff::fixed(Duration::from_millis(1)));
+    let modio = Modio::builder(Credentials::with_token(API_KEY, ACCESS_TOKEN))
+        .host("http://127.0.0.1:1/v1")
+        .retry(policy)
+        .build()?;
+
+    let err = modio.games().search(Filter::default()).first_page().await;
+    let err = err.expect_err("connection refused");
+    assert!(!err.to_string().contains(API_KEY));
+
+    output.assert_redacted();
+    Ok(())
+}
+
+#[tokio::test]
+async fn signed_download_url() -> modio::Result<()> {
+    let server = Server::run();
+    let path = format!("/v1/games/1/mods/2/files/3/download/{}", SIGNATURE);
+    server.expect(
+        Expectation::matching(request::method_path("GET", path.clone()))
+            .times(2)
+            .respond_with(status_code(410)),
+    );
+
+    let file = serde_json::json!({
+        "id": 3,
+        "mod_id": 2,
+        "date_added": 0,
+        "date_scanned": 0,
+        "virus_status": 1,
+        "virus_positive": 0,
+        "virustotal_hash": null,
+        "filesize": 0,
+        "filehash": {"md5": ""},
+        "filename": "mod.zip",
+        "version": null,
+        "changelog": null,
+        "metadata_blob": null,
+        "download": {"binary_url": server.url_str(&path), "date_expires": 0},
+        "platforms": [],
+    });
+
+    // The expired url is resolved again, which returns the same url.
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games/1/mods/2/files/3"))
+            .respond_with(json_encoded(file.clone())),
+    );
+    let file = serde_json::from_value::<modio::files::File>(file).expect("valid modfile");
+
+    let output = Output::default();
+    let _guard = output.capture();
+
+    let modio = Modio::host(server.url_str("/v1"), API_KEY)?;
+    let err = modio.download(DownloadAction::from(file)).bytes().await;
+    let err = err.expect_err("gone");
+    assert!(!err.to_string().contains(SIGNATURE));
+
+    output.assert_redacted();
+    Ok(())
+}
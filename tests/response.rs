@@ -0,0 +1,125 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use httptest::{matchers::*, responders::*};
+use httptest::{Expectation, Server};
+
+use modio::filter::Filter;
+use modio::{Modio, ResponseMeta, Result};
+
+const EMPTY_LIST: &str =
+    r#"{"data":[],"result_count":0,"result_offset":0,"result_limit":100,"result_total":0}"#;
+const NOT_FOUND: &str = r#"{"error":{"code":404,"error_ref":14000,"message":"Not found"}}"#;
+
+fn client(server: &Server) -> Result<(Modio, Arc<Mutex<Vec<ResponseMeta>>>)> {
+    let responses = Arc::new(Mutex::new(Vec::new()));
+    let captured = Arc::clone(&responses);
+
+    let modio = Modio::builder("foobar")
+        .host(server.url_str("/v1"))
+        .on_response(move |meta| captured.lock().unwrap().push(meta.clone()))
+        .build()?;
+    Ok((modio, responses))
+}
+
+#[tokio::test]
+async fn meta_of_successful_response() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games")).respond_with(
+            status_code(200)
+                .insert_header("x-modio-request-id", "req-123")
+                .insert_header("x-ratelimit-limit", "60")
+                .insert_header("x-ratelimit-remaining", "59")
+                .body(EMPTY_LIST),
+        ),
+    );
+
+    let (modio, responses) = client(&server)?;
+    modio.games().search(Filter::default()).first_page().await?;
+
+    let responses = responses.lock().unwrap();
+    assert_eq!(responses.len(), 1);
+
+    let meta = &responses[0];
+    assert_eq!(meta.status.as_u16(), 200);
+    assert_eq!(meta.request_id.as_deref(), Some("req-123"));
+    assert_eq!(meta.ratelimit_limit, Some(60));
+    assert_eq!(meta.ratelimit_remaining, Some(59));
+    assert_eq!(meta.ratelimit_retry_after, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn request_id_of_failed_response() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games/1")).respond_with(
+            status_code(404)
+                .insert_header("x-modio-request-id", "req-404")
+                .body(NOT_FOUND),
+        ),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games")).respond_with(
+            status_code(429)
+                .insert_header("x-modio-request-id", "req-429")
+                .insert_header("x-ratelimit-remaining", "0")
+                .insert_header("x-ratelimit-retryafter", "42"),
+        ),
+    );
+
+    let (modio, responses) = client(&server)?;
+
+    let err = modio.game(1).get().await.expect_err("not found");
+    assert_eq!(err.request_id(), Some("req-404"));
+    assert_eq!(err.error_ref(), Some(14000));
+
+    let err = modio.games().search(Filter::default()).first_page().await;
+    let err = err.expect_err("ratelimited");
+    assert!(err.is_ratelimited());
+    assert_eq!(err.request_id(), Some("req-429"));
+
+    let responses = responses.lock().unwrap();
+    assert_eq!(responses.len(), 2);
+    assert_eq!(
+        responses[1].ratelimit_retry_after,
+        Some(Duration::from_secs(42))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn capture_meta_of_single_call() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games")).respond_with(
+            status_code(200)
+                .insert_header("x-modio-request-id", "req-1")
+                .insert_header("x-ratelimit-remaining", "58")
+                .body(EMPTY_LIST),
+        ),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games/1")).respond_with(
+            status_code(404)
+                .insert_header("x-modio-request-id", "req-2")
+                .body(NOT_FOUND),
+        ),
+    );
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let search = modio.games().search(Filter::default()).first_page();
+    let (games, meta) = ResponseMeta::capture(search).await?;
+    assert!(games.is_empty());
+
+    let meta = meta.expect("response metadata");
+    assert_eq!(meta.status.as_u16(), 200);
+    assert_eq!(meta.request_id.as_deref(), Some("req-1"));
+    assert_eq!(meta.ratelimit_remaining, Some(58));
+
+    let err = ResponseMeta::capture(modio.game(1).get()).await;
+    let err = err.expect_err("not found");
+    assert_eq!(err.request_id(), Some("req-2"));
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use httptest::{cycle, Expectation, Server};
+use httptest::{matchers::*, responders::*};
+
+use modio::filter::Filter;
+use modio::retry::{Backoff, RetryOn, RetryPolicy};
+use modio::{Modio, Result};
+
+const EMPTY_LIST: &str =
+    r#"{"data":[],"result_count":0,"result_offset":0,"result_limit":100,"result_total":0}"#;
+const MESSAGE: &str = r#"{"code":200,"message":"Email sent"}"#;
+
+fn policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy::new(max_attempts).backoff(Backoff::fixed(Duration::from_millis(1)))
+}
+
+fn client(server: &Server, policy: RetryPolicy) -> Result<Modio> {
+    Modio::builder("foobar")
+        .host(server.url_str("/v1"))
+        .retry(policy)
+        .build()
+}
+
+#[tokio::test]
+async fn no_retries_by_default() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games"))
+            .times(1)
+            .respond_with(status_code(503)),
+    );
+
+    let modio = Modio::host(server.url_str("/v1"), "foobar")?;
+    let err = modio.games().search(Filter::default()).first_page().await;
+
+    let err = err.expect_err("request should fail");
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(503));
+    Ok(())
+}
+
+#[tokio::test]
+async fn retry_server_errors() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games"))
+            .times(3)
+            .respond_with(cycle![
+                status_code(502).body("<html>Bad Gateway</html>"),
+                status_code(503),
+                status_code(200).body(EMPTY_LIST),
+            ]),
+    );
+
+    let modio = client(&server, policy(3))?;
+    let list = modio.games().search(Filter::default()).first_page().await?;
+
+    assert!(list.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn give_up_after_max_attempts() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games"))
+            .times(2)
+            .respond_with(status_code(500)),
+    );
+
+    let modio = client(&server, policy(2))?;
+    let err = modio.games().search(Filter::default()).first_page().await;
+
+    assert!(err.expect_err("request should fail").is_status());
+    Ok(())
+}
+
+#[tokio::test]
+async fn retry_ratelimited() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games"))
+            .times(2)
+            .respond_with(cycle![
+                status_code(429)
+                    .insert_header("x-ratelimit-remaining", "0")
+                    .insert_header("x-ratelimit-retryafter", "0"),
+                status_code(200).body(EMPTY_LIST),
+            ]),
+    );
+
+    let modio = client(&server, policy(2))?;
+    let list = modio.games().search(Filter::default()).first_page().await?;
+
+    assert!(list.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn skip_disabled_kinds() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/v1/games"))
+            .times(1)
+            .respond_with(
+                status_code(429)
+                    .insert_header("x-ratelimit-remaining", "0")
+                    .insert_header("x-ratelimit-retryafter", "0"),
+            ),
+    );
+
+    let modio = client(&server, policy(3).retry_on(RetryOn::SERVER_ERROR))?;
+    let err = modio.games().search(Filter::default()).first_page().await;
+
+    assert!(err.expect_err("request should fail").is_ratelimited());
+    Ok(())
+}
+
+#[tokio::test]
+async fn no_retry_for_post() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("POST", "/v1/oauth/emailrequest"))
+            .times(1)
+            .respond_with(status_code(503)),
+    );
+
+    let modio = client(&server, policy(3))?;
+    let err = modio.auth().request_code("john@example.com").await;
+
+    assert!(err.expect_err("request should fail").is_status());
+    Ok(())
+}
+
+#[tokio::test]
+async fn retry_post_if_enabled() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("POST", "/v1/oauth/emailrequest"))
+            .times(2)
+            .respond_with(cycle![status_code(503), status_code(200).body(MESSAGE)]),
+    );
+
+    let modio = client(&server, policy(3).non_idempotent(true))?;
+    modio.auth().request_code("john@example.com").await?;
+    Ok(())
+}
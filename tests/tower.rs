@@ -0,0 +1,82 @@
+#![cfg(feature = "tower")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use http::{Method, StatusCode};
+use tower::util::MapRequestLayer;
+use tower::ServiceBuilder;
+
+use modio::transport::{BoxError, MemoryTransport, Request, Response};
+use modio::Modio;
+
+const EMAIL_SENT: &str = r#"{"code":200,"message":"Email sent"}"#;
+
+#[tokio::test]
+async fn layers_wrap_transport() -> modio::Result<()> {
+    let count = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&count);
+
+    let transport = MemoryTransport::new().json(
+        Method::POST,
+        "/v1/oauth/emailrequest",
+        StatusCode::OK,
+        EMAIL_SENT,
+    );
+    let builder = Modio::builder("foobar")
+        .transport(transport)
+        .layer(MapRequestLayer::new(move |req: Request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            req
+        }))
+        .layer(ServiceBuilder::new().concurrency_limit(1));
+    // The builder can be moved across threads with layers.
+    let modio = std::thread::spawn(move || builder.build())
+        .join()
+        .unwrap()?;
+
+    modio.auth().request_code("john@example.com").await?;
+    modio.auth().request_code("john@example.com").await?;
+
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn timeout_layer() -> modio::Result<()> {
+    let transport = |_: Request| async {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        let resp = http::Response::new(EMAIL_SENT);
+        Ok::<_, BoxError>(Response::from(resp))
+    };
+    let modio = Modio::builder("foobar")
+        .transport(transport)
+        .layer(ServiceBuilder::new().timeout(Duration::from_secs(5)))
+        .build()?;
+
+    let err = modio.auth().request_code("john@example.com").await;
+    let err = err.expect_err("timeout");
+    assert!(err.to_string().contains("timed out"), "{}", err);
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn rate_limit_layer() -> modio::Result<()> {
+    let transport = MemoryTransport::new().json(
+        Method::POST,
+        "/v1/oauth/emailrequest",
+        StatusCode::OK,
+        EMAIL_SENT,
+    );
+    let modio = Modio::builder("foobar")
+        .transport(transport)
+        .layer(ServiceBuilder::new().rate_limit(1, Duration::from_secs(10)))
+        .build()?;
+
+    // The second request waits for the next period of the shared rate limit.
+    let start = tokio::time::Instant::now();
+    modio.auth().request_code("john@example.com").await?;
+    modio.auth().request_code("john@example.com").await?;
+    assert!(start.elapsed() >= Duration::from_secs(10));
+    Ok(())
+}
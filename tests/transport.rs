@@ -0,0 +1,139 @@
+use std::sync::{Arc, Mutex};
+
+use futures_util::{future, TryStreamExt};
+use http::{Method, StatusCode};
+
+use modio::download::DownloadAction;
+use modio::files::AddFileOptions;
+use modio::filter::Filter;
+use modio::transport::{body_stream, BoxError, MemoryTransport, Request, Response};
+use modio::{Modio, TargetPlatform};
+
+const FILES_PATH: &str = "/v1/games/1/mods/2/files";
+const BINARY_URL: &str = "https://binary.modcdn.io/mods/2/files/3/mod.zip";
+
+fn file() -> String {
+    serde_json::json!({
+        "id": 3,
+        "mod_id": 2,
+        "date_added": 0,
+        "date_scanned": 0,
+        "virus_status": 1,
+        "virus_positive": 0,
+        "virustotal_hash": null,
+        "filesize": 5,
+        "filehash": {"md5": ""},
+        "filename": "mod.zip",
+        "version": "1.0",
+        "changelog": null,
+        "metadata_blob": null,
+        "download": {"binary_url": BINARY_URL, "date_expires": 0},
+        "platforms": [],
+    })
+    .to_string()
+}
+
+#[tokio::test]
+async fn memory_transport() -> modio::Result<()> {
+    let list = format!(
+        r#"{{"data":[{}],"result_count":1,"result_offset":0,"result_limit":100,"result_total":1}}"#,
+        file()
+    );
+    let transport = MemoryTransport::new()
+        .json(
+            Method::POST,
+            "/v1/oauth/emailexchange",
+            StatusCode::OK,
+            r#"{"code":200,"access_token":"token","date_expires":1}"#,
+        )
+        .json(Method::GET, FILES_PATH, StatusCode::OK, list)
+        .route(Method::GET, "/mods/2/files/3/mod.zip", |_| {
+            http::Response::new("hello")
+        });
+    let modio = Modio::builder("foobar").transport(transport).build()?;
+
+    let creds = modio.auth().security_code("QWERT").await?;
+    assert_eq!(creds.token.as_ref().map(|t| &*t.value), Some("token"));
+    let modio = modio.with_credentials(creds);
+
+    let files = modio.mod_(1, 2).files();
+    let files = files.search(Filter::default()).collect().await?;
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].version.as_deref(), Some("1.0"));
+
+    let file = files.into_iter().next().expect("file");
+    let action = DownloadAction::FileObj(Box::new(file));
+    let bytes = modio.download(action).bytes().await?;
+    assert_eq!(&bytes[..], b"hello");
+    Ok(())
+}
+
+#[tokio::test]
+async fn missing_route() -> modio::Result<()> {
+    let modio = Modio::builder("foobar")
+        .transport(MemoryTransport::new())
+        .build()?;
+
+    let err = modio.game(1).get().await.expect_err("no route");
+    assert!(err.to_string().contains("no route for GET /v1/games/1"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn custom_transport() -> modio::Result<()> {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let captured = Arc::clone(&requests);
+
+    let transport = move |mut req: Request| {
+        let captured = Arc::clone(&captured);
+        async move {
+            let body = match req.body_mut().take() {
+                Some(body) => {
+                    body_stream(body)
+                        .try_fold(Vec::new(), |mut buf, chunk| {
+                            buf.extend_from_slice(&chunk);
+                            future::ok(buf)
+                        })
+                        .await?
+                }
+                None => Vec::new(),
+            };
+            captured.lock().unwrap().push((
+                req.method().clone(),
+                req.url().clone(),
+                req.headers().clone(),
+                body,
+            ));
+            let resp = http::Response::builder().status(201).body(file())?;
+            Ok::<_, BoxError>(Response::from(resp))
+        }
+    };
+    let modio = Modio::builder(modio::Credentials::with_token("foobar", "token"))
+        .host("https://api.mod.io/v1")
+        .target_platform(TargetPlatform::Windows)
+        .transport(transport)
+        .build()?;
+
+    let options = AddFileOptions::with_read(&b"hello"[..], "mod.zip").version("1.0");
+    let file = modio.mod_(1, 2).files().add(options).await?;
+    assert_eq!(file.id, 3);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+
+    let (method, url, headers, body) = &requests[0];
+    assert_eq!(method, Method::POST);
+    assert_eq!(url.path(), FILES_PATH);
+    assert_eq!(url.query(), Some("api_key=foobar"));
+    assert_eq!(headers["authorization"], "Bearer token");
+    assert_eq!(headers["x-modio-platform"], "Windows");
+    assert!(headers["user-agent"]
+        .to_str()
+        .unwrap()
+        .starts_with("modio/"));
+
+    let body = String::from_utf8_lossy(body);
+    assert!(body.contains(r#"filename="mod.zip""#));
+    assert!(body.contains("hello"));
+    Ok(())
+}
@@ -0,0 +1,271 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use httptest::{all_of, cycle, Expectation, Server};
+use httptest::{matchers::*, responders::*};
+use serde_json::json;
+
+use modio::files::{AddFileOptions, UploadStatus};
+use modio::mods::AddMediaOptions;
+use modio::retry::{Backoff, RetryPolicy};
+use modio::{CancelHandle, Credentials, Modio, Result, UploadProgress};
+
+const CONTENT: &[u8] = b"0123456789";
+/// End of central directory record of an empty zip archive.
+const EMPTY_ZIP: &[u8] = b"PK\x05\x06\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+const SESSION_PATH: &str = "/v1/games/1/mods/2/files/multipart";
+
+fn part(number: u32, size: u64) -> serde_json::Value {
+    json!({
+        "upload_id": "abc",
+        "part_number": number,
+        "part_size": size,
+        "date_added": 0,
+    })
+}
+
+fn list(data: Vec<serde_json::Value>) -> serde_json::Value {
+    json!({
+        "data": data,
+        "result_count": data.len(),
+        "result_offset": 0,
+        "result_limit": 100,
+        "result_total": data.len(),
+    })
+}
+
+fn file() -> serde_json::Value {
+    json!({
+        "id": 3,
+        "mod_id": 2,
+        "date_added": 0,
+        "date_scanned": 0,
+        "virus_status": 0,
+        "virus_positive": 0,
+        "virustotal_hash": null,
+        "filesize": CONTENT.len(),
+        "filehash": {"md5": format!("{:x}", md5::compute(CONTENT))},
+        "filename": "mod.zip",
+        "version": "1.2.0",
+        "changelog": null,
+        "metadata_blob": null,
+        "download": {"binary_url": "https://example.com/mod.zip", "date_expires": 0},
+        "platforms": [],
+    })
+}
+
+fn upload_part(range: &'static str) -> impl Matcher<httptest::http::Request<bytes::Bytes>> {
+    all_of![
+        request::method_path("PUT", SESSION_PATH),
+        request::query(url_decoded(contains(("upload_id", "abc")))),
+        request::headers(contains(("content-range", range))),
+    ]
+}
+
+#[tokio::test]
+async fn multipart_upload() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", SESSION_PATH),
+            request::body(url_decoded(contains(("filename", "mod.zip")))),
+        ])
+        .respond_with(json_encoded(json!({"upload_id": "abc", "status": 0}))),
+    );
+    // The first part was uploaded before the upload was interrupted.
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", SESSION_PATH),
+            request::query(url_decoded(contains(("upload_id", "abc")))),
+        ])
+        .respond_with(json_encoded(list(vec![part(1, 4)]))),
+    );
+    server.expect(
+        Expectation::matching(all_of![upload_part("bytes 4-7/10"), request::body("4567")])
+            .times(2)
+            .respond_with(cycle![status_code(503), json_encoded(part(2, 4))]),
+    );
+    server.expect(
+        Expectation::matching(all_of![upload_part("bytes 8-9/10"), request::body("89")])
+            .respond_with(json_encoded(part(3, 2))),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", "/v1/games/1/mods/2/files/multipart/complete"),
+            request::query(url_decoded(contains(("upload_id", "abc")))),
+        ])
+        .respond_with(json_encoded(json!({"upload_id": "abc", "status": 1}))),
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mod.zip");
+    std::fs::write(&path, CONTENT).unwrap();
+
+    let modio = Modio::host(
+        server.url_str("/v1"),
+        Credentials::with_token("foobar", "token"),
+    )?;
+    let files = modio.mod_(1, 2).files();
+    let session = files.create_upload_session("mod.zip").await?;
+    assert_eq!(session.status, UploadStatus::Incomplete);
+
+    let policy = RetryPolicy::new(2).backoff(Backoff::fixed(Duration::from_millis(1)));
+    let session = files
+        .multipart_upload(session.upload_id)
+        .part_size(4)
+        .retry(policy)
+        .upload_file(&path)
+        .await?;
+    assert_eq!(session.upload_id, "abc");
+    assert_eq!(session.status, UploadStatus::Pending);
+    Ok(())
+}
+
+#[tokio::test]
+async fn add_file_from_upload() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", "/v1/games/1/mods/2/files"),
+            request::body(matches("name=\"upload_id\"\r\n\r\nabc\r\n")),
+            request::body(matches("name=\"version\"\r\n\r\n1.2.0\r\n")),
+        ])
+        .respond_with(json_encoded(file())),
+    );
+
+    let modio = Modio::host(
+        server.url_str("/v1"),
+        Credentials::with_token("foobar", "token"),
+    )?;
+    let options = AddFileOptions::with_upload("abc").version("1.2.0");
+    let file = modio.mod_(1, 2).files().add(options).await?;
+    assert_eq!(file.id, 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn upload_progress() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("POST", "/v1/games/1/mods/2/files"))
+            .respond_with(json_encoded(file())),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("POST", "/v1/games/1/mods/2/media"))
+            .respond_with(json_encoded(json!({"code": 201, "message": "added"}))),
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mod.zip");
+    std::fs::write(&path, EMPTY_ZIP).unwrap();
+    let image = dir.path().join("image.png");
+    std::fs::write(&image, b"image").unwrap();
+
+    let modio = Modio::host(
+        server.url_str("/v1"),
+        Credentials::with_token("foobar", "token"),
+    )?;
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let track = |progress: &Arc<Mutex<Vec<_>>>| {
+        let progress = Arc::clone(progress);
+        move |p: &UploadProgress| progress.lock().unwrap().push((p.uploaded, p.total))
+    };
+
+    let options = AddFileOptions::with_file(&path).progress(track(&progress));
+    modio.mod_(1, 2).files().add(options).await?;
+    let last = progress.lock().unwrap().pop();
+    assert_eq!(last, Some((22, Some(22))));
+
+    let options = AddMediaOptions::default()
+        .logo(&image)
+        .images(&[&path])
+        .progress(track(&progress));
+    modio.mod_(1, 2).add_media(options).await?;
+    let last = progress.lock().unwrap().pop();
+    assert_eq!(last, Some((27, Some(27))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancel_upload() -> Result<()> {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("POST", "/v1/games/1/mods/2/files"))
+            .times(..)
+            .respond_with(json_encoded(file())),
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mod.zip");
+    std::fs::write(&path, EMPTY_ZIP).unwrap();
+
+    let modio = Modio::host(
+        server.url_str("/v1"),
+        Credentials::with_token("foobar", "token"),
+    )?;
+    let cancel = CancelHandle::new();
+    cancel.cancel();
+    let options = AddFileOptions::with_file(&path).cancel_handle(cancel);
+    let err = modio.mod_(1, 2).files().add(options).await.unwrap_err();
+    assert!(err.is_cancelled());
+    Ok(())
+}
+
+#[tokio::test]
+async fn compute_filehash() -> Result<()> {
+    let server = Server::run();
+    let filehash = format!("name=\"filehash\"\r\n\r\n{:x}\r\n", md5::compute(EMPTY_ZIP));
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", "/v1/games/1/mods/2/files"),
+            request::body(matches(filehash.as_str())),
+        ])
+        .respond_with(json_encoded(file())),
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mod.zip");
+    std::fs::write(&path, EMPTY_ZIP).unwrap();
+
+    let modio = Modio::host(
+        server.url_str("/v1"),
+        Credentials::with_token("foobar", "token"),
+    )?;
+    let options = AddFileOptions::with_file(&path).compute_filehash(true);
+    modio.mod_(1, 2).files().add(options).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn validate_before_upload() -> Result<()> {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mod.zip");
+    std::fs::write(&path, CONTENT).unwrap();
+    let zip = dir.path().join("empty.zip");
+    std::fs::write(&zip, EMPTY_ZIP).unwrap();
+
+    // No request is sent for invalid files.
+    let modio = Modio::host(
+        "http://127.0.0.1:0/v1",
+        Credentials::with_token("foobar", "token"),
+    )?;
+    let files = modio.mod_(1, 2).files();
+
+    let options = AddFileOptions::with_file(&path).validate(true);
+    let err = files.add(options).await.unwrap_err();
+    assert!(err.is_upload());
+    assert_eq!(
+        err.to_string(),
+        "upload error: File 'mod.zip' is not a zip archive."
+    );
+
+    let files = modio.mod_(1, 2).files();
+    let options = AddFileOptions::with_file(&zip).max_filesize(10);
+    let err = files.add(options).await.unwrap_err();
+    assert!(err.is_upload());
+    assert_eq!(
+        err.to_string(),
+        "upload error: File 'empty.zip' is too large, 22 bytes exceed the limit of 10 bytes."
+    );
+    Ok(())
+}